@@ -2,7 +2,9 @@ use crate::modules::ModuleUpdateEvent;
 use crate::spawn;
 use smithay_client_toolkit::reexports::calloop;
 use std::fmt::Debug;
+use std::time::Duration;
 use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
 
 pub trait SyncSenderExt<T> {
     /// Asynchronously sends a message on the channel,
@@ -70,6 +72,24 @@ pub trait AsyncSenderExt<T>: Sync + Send + Sized + Clone {
         self.send_expect(ModuleUpdateEvent::Update(update)).await;
     }
 
+    /// Like [`AsyncSenderExt::send_update`],
+    /// but only sends the update if it differs from `last`.
+    ///
+    /// `last` holds the last update sent,
+    /// and is replaced with `update` whenever it is sent.
+    #[inline]
+    async fn send_update_if_changed<U: Clone + PartialEq>(&self, update: U, last: &mut Option<U>)
+    where
+        Self: AsyncSenderExt<ModuleUpdateEvent<U>>,
+    {
+        if last.as_ref() == Some(&update) {
+            return;
+        }
+
+        last.replace(update.clone());
+        self.send_expect(ModuleUpdateEvent::Update(update)).await;
+    }
+
     /// Shorthand for [`AsyncSenderExt::send_spawn`]
     /// when sending a [`ModuleUpdateEvent::Update`].
     #[inline]
@@ -148,6 +168,19 @@ where
         D::Target: 'static,
         Fn: FnMut(&D::Target, T) -> F + 'static,
         F: Future;
+
+    /// Collapses bursts of messages so that at most one is received per `interval`.
+    ///
+    /// The first message in a burst starts the interval,
+    /// and only the latest message received during it is passed on once it ends.
+    /// Any messages in between are dropped,
+    /// so this should only be used where each message replaces the previous one,
+    /// rather than describing a change to it.
+    ///
+    /// Returns a new receiver, which can be used with [`BroadcastReceiverExt::recv_glib`].
+    fn throttle(self, interval: Duration) -> broadcast::Receiver<T>
+    where
+        T: Send;
 }
 
 impl<T> BroadcastReceiverExt<T> for broadcast::Receiver<T>
@@ -206,6 +239,48 @@ where
             }
         });
     }
+
+    fn throttle(mut self, interval: Duration) -> broadcast::Receiver<T>
+    where
+        T: Send,
+    {
+        let (tx, rx) = broadcast::channel(32);
+
+        spawn(async move {
+            loop {
+                let mut latest = match self.recv().await {
+                    Ok(val) => val,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let deadline = sleep(interval);
+                tokio::pin!(deadline);
+
+                let mut closed = false;
+                loop {
+                    tokio::select! {
+                        () = &mut deadline => break,
+                        res = self.recv() => match res {
+                            Ok(val) => latest = val,
+                            Err(broadcast::error::RecvError::Lagged(_)) => {}
+                            Err(broadcast::error::RecvError::Closed) => {
+                                closed = true;
+                                break;
+                            }
+                        },
+                    }
+                }
+
+                // stop once every throttled receiver has been dropped
+                if tx.send(latest).is_err() || closed {
+                    break;
+                }
+            }
+        });
+
+        rx
+    }
 }
 
 /// `recv_glib` callback dependency
@@ -259,3 +334,23 @@ impl_dependency!(0 T1, 1 T2, 2 T3, 3 T4, 4 T5, 5 T6, 6 T7, 7 T8, 8 T9);
 impl_dependency!(0 T1, 1 T2, 2 T3, 3 T4, 4 T5, 5 T6, 6 T7, 7 T8, 8 T9, 9 T10);
 impl_dependency!(0 T1, 1 T2, 2 T3, 3 T4, 4 T5, 5 T6, 6 T7, 7 T8, 8 T9, 9 T10, 10 T11);
 impl_dependency!(0 T1, 1 T2, 2 T3, 3 T4, 4 T5, 5 T6, 6 T7, 7 T8, 8 T9, 9 T10, 10 T11, 11 T12);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn throttle_keeps_latest_in_burst() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut rx = rx.throttle(Duration::from_millis(50));
+
+        for val in 0..3 {
+            tx.send(val).unwrap();
+        }
+
+        assert_eq!(rx.recv().await.unwrap(), 2);
+
+        drop(tx);
+        assert!(rx.recv().await.is_err());
+    }
+}
@@ -0,0 +1,143 @@
+mod dbus;
+
+use crate::clients::ClientResult;
+use crate::register_fallible_client;
+use color_eyre::Result;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs;
+
+pub use dbus::SessionProxy;
+
+/// The sysfs directory containing a subdirectory for each backlight device.
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// The brightness of a single backlight device,
+/// in the device's own units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brightness {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Brightness {
+    /// Gets the brightness as a percentage of the maximum.
+    pub fn percentage(self) -> f64 {
+        if self.max == 0 {
+            return 0.0;
+        }
+
+        f64::from(self.current) / f64::from(self.max) * 100.0
+    }
+
+    /// Gets the raw brightness value for the provided percentage of the maximum.
+    pub fn value_for(self, percentage: f64) -> u32 {
+        (f64::from(self.max) * percentage.clamp(0.0, 100.0) / 100.0).round() as u32
+    }
+
+    /// Gets the raw brightness value to step to when moving towards the provided percentage.
+    ///
+    /// Unlike [`Brightness::value_for`], this always changes the value by at least one unit,
+    /// as a small step can otherwise round back to the current value
+    /// on devices with a low maximum.
+    pub fn step_towards(self, percentage: f64) -> u32 {
+        let value = self.value_for(percentage);
+        if value != self.current {
+            return value;
+        }
+
+        if percentage > self.percentage() {
+            (self.current + 1).min(self.max)
+        } else if percentage < self.percentage() {
+            self.current.saturating_sub(1)
+        } else {
+            value
+        }
+    }
+}
+
+/// Creates a proxy for the current logind session on the provided system bus connection.
+///
+/// The session is used to change the brightness,
+/// which logind allows without root permissions.
+pub async fn create_session_proxy(dbus: &zbus::Connection) -> ClientResult<SessionProxy<'static>> {
+    let proxy = SessionProxy::new(dbus).await?;
+
+    Ok(Arc::new(proxy))
+}
+
+/// Finds the backlight device with the provided name,
+/// or the first device alphabetically if no name is provided.
+///
+/// Returns `None` if no matching device exists.
+pub async fn find_device(name: Option<&str>) -> Result<Option<String>> {
+    if let Some(name) = name {
+        let exists = fs::try_exists(Path::new(BACKLIGHT_DIR).join(name)).await?;
+        return Ok(exists.then(|| name.to_string()));
+    }
+
+    let mut entries = fs::read_dir(BACKLIGHT_DIR).await?;
+    let mut names = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        names.push(entry.file_name().to_string_lossy().to_string());
+    }
+
+    names.sort();
+    Ok(names.into_iter().next())
+}
+
+/// Reads the current brightness of the device from sysfs.
+pub async fn read_brightness(device: &str) -> Result<Brightness> {
+    let dir = Path::new(BACKLIGHT_DIR).join(device);
+
+    let current = fs::read_to_string(dir.join("brightness")).await?;
+    let max = fs::read_to_string(dir.join("max_brightness")).await?;
+
+    Ok(Brightness {
+        current: current.trim().parse()?,
+        max: max.trim().parse()?,
+    })
+}
+
+/// Sets the raw brightness of the device through logind.
+pub async fn set_brightness(
+    session: &SessionProxy<'static>,
+    device: &str,
+    value: u32,
+) -> Result<()> {
+    session.set_brightness("backlight", device, value).await?;
+    Ok(())
+}
+
+register_fallible_client!(SessionProxy<'static>, brightness);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_percentage() {
+        let brightness = Brightness {
+            current: 60,
+            max: 120,
+        };
+
+        assert_eq!(brightness.percentage(), 50.0);
+        assert_eq!(brightness.value_for(25.0), 30);
+        assert_eq!(brightness.value_for(150.0), 120);
+    }
+
+    #[test]
+    fn steps_at_least_one_unit() {
+        let brightness = Brightness { current: 3, max: 7 };
+
+        assert_eq!(brightness.step_towards(brightness.percentage() + 5.0), 4);
+        assert_eq!(brightness.step_towards(brightness.percentage() - 5.0), 2);
+        assert_eq!(brightness.step_towards(brightness.percentage()), 3);
+        assert_eq!(brightness.step_towards(100.0), 7);
+
+        let brightness = Brightness { current: 7, max: 7 };
+        assert_eq!(brightness.step_towards(105.0), 7);
+    }
+}
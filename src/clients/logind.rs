@@ -0,0 +1,27 @@
+use crate::clients::ClientResult;
+use crate::register_fallible_client;
+use std::sync::Arc;
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait Manager {
+    /// PrepareForSleep signal
+    ///
+    /// Sent with `start` set before the system suspends,
+    /// and again with it unset once the system resumes.
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+/// Creates a proxy for the logind manager on the provided system bus connection.
+pub async fn create_proxy(dbus: &zbus::Connection) -> ClientResult<ManagerProxy<'static>> {
+    let proxy = ManagerProxy::new(dbus).await?;
+
+    Ok(Arc::new(proxy))
+}
+
+register_fallible_client!(ManagerProxy<'static>, logind);
@@ -1,10 +1,17 @@
 use crate::{Ironbar, await_sync};
 use color_eyre::Result;
+use std::any::type_name;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
 
+#[cfg(feature = "brightness")]
+pub mod brightness;
 #[cfg(feature = "clipboard")]
 pub mod clipboard;
 #[cfg(any(
@@ -16,12 +23,16 @@ pub mod clipboard;
 pub mod compositor;
 #[cfg(feature = "keyboard")]
 pub mod libinput;
+#[cfg(feature = "upower")]
+pub mod logind;
 #[cfg(feature = "cairo")]
 pub mod lua;
 #[cfg(feature = "music")]
 pub mod music;
 #[cfg(feature = "network_manager")]
 pub mod networkmanager;
+#[cfg(feature = "upower")]
+pub mod power_profiles;
 #[cfg(feature = "sway")]
 pub mod sway;
 #[cfg(feature = "notifications")]
@@ -49,12 +60,16 @@ pub struct Clients {
     hyprland: Option<Arc<compositor::hyprland::Client>>,
     #[cfg(feature = "bindmode")]
     bindmode: Option<Arc<dyn compositor::BindModeClient>>,
+    #[cfg(feature = "brightness")]
+    brightness: Option<Arc<brightness::SessionProxy<'static>>>,
     #[cfg(feature = "clipboard")]
     clipboard: Option<Arc<clipboard::Client>>,
     #[cfg(feature = "keyboard")]
     libinput: HashMap<Box<str>, Arc<libinput::Client>>,
     #[cfg(feature = "keyboard")]
     keyboard_layout: Option<Arc<dyn compositor::KeyboardLayoutClient>>,
+    #[cfg(feature = "upower")]
+    logind: Option<Arc<logind::ManagerProxy<'static>>>,
     #[cfg(feature = "cairo")]
     lua: Option<Rc<lua::LuaEngine>>,
     #[cfg(feature = "music")]
@@ -63,18 +78,31 @@ pub struct Clients {
     network_manager: Option<Arc<networkmanager::Client>>,
     #[cfg(feature = "notifications")]
     notifications: Option<Arc<swaync::Client>>,
+    #[cfg(feature = "upower")]
+    power_profiles: Option<Arc<power_profiles::PowerProfilesProxy<'static>>>,
     #[cfg(feature = "sys_info")]
     sys_info: Option<Arc<sysinfo::Client>>,
+    #[cfg(any(
+        feature = "brightness",
+        feature = "network_manager",
+        feature = "upower"
+    ))]
+    system_bus: Option<Arc<zbus::Connection>>,
     #[cfg(feature = "tray")]
     tray: Option<Arc<tray::Client>>,
     #[cfg(feature = "upower")]
-    upower: Option<Arc<zbus::fdo::PropertiesProxy<'static>>>,
+    upower: Option<Arc<upower::UPowerProxy<'static>>>,
+    #[cfg(feature = "upower")]
+    upower_devices: Option<Arc<upower::DeviceStore>>,
     #[cfg(feature = "volume")]
     volume: Option<Arc<volume::Client>>,
 }
 
 pub type ClientResult<T> = Result<Arc<T>>;
 
+/// The shortest time to wait between attempts to create a client.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 impl Clients {
     pub(crate) fn new() -> Self {
         Self::default()
@@ -86,6 +114,20 @@ impl Clients {
             .clone()
     }
 
+    #[cfg(feature = "brightness")]
+    pub fn brightness(&mut self) -> ClientResult<brightness::SessionProxy<'static>> {
+        let client = if let Some(client) = &self.brightness {
+            client.clone()
+        } else {
+            let dbus = self.system_bus()?;
+            let client = await_sync(async move { brightness::create_session_proxy(&dbus).await })?;
+            self.brightness.replace(client.clone());
+            client
+        };
+
+        Ok(client)
+    }
+
     #[cfg(feature = "clipboard")]
     pub fn clipboard(&mut self) -> Arc<clipboard::Client> {
         let wayland = self.wayland();
@@ -159,6 +201,20 @@ impl Clients {
         }
     }
 
+    #[cfg(feature = "upower")]
+    pub fn logind(&mut self) -> ClientResult<logind::ManagerProxy<'static>> {
+        let client = if let Some(client) = &self.logind {
+            client.clone()
+        } else {
+            let dbus = self.system_bus()?;
+            let client = await_sync(async move { logind::create_proxy(&dbus).await })?;
+            self.logind.replace(client.clone());
+            client
+        };
+
+        Ok(client)
+    }
+
     #[cfg(feature = "cairo")]
     pub fn lua(&mut self, config_dir: &Path) -> Rc<lua::LuaEngine> {
         self.lua
@@ -190,7 +246,8 @@ impl Clients {
         if let Some(client) = &self.network_manager {
             Ok(client.clone())
         } else {
-            let client = await_sync(async move { networkmanager::create_client().await })?;
+            let dbus = self.system_bus()?;
+            let client = await_sync(async move { networkmanager::create_client(&dbus).await })?;
             self.network_manager = Some(client.clone());
             Ok(client)
         }
@@ -224,6 +281,69 @@ impl Clients {
             .clone()
     }
 
+    /// Discards the shared system bus connection if it has been lost,
+    /// such as when the bus restarts,
+    /// so that the next client to use it opens a fresh connection.
+    fn check_system_bus(&mut self) {
+        #[cfg(any(
+            feature = "brightness",
+            feature = "network_manager",
+            feature = "upower"
+        ))]
+        if let Some(dbus) = self.system_bus.clone() {
+            let is_connected = await_sync(async move {
+                dbus.call_method(
+                    Some("org.freedesktop.DBus"),
+                    "/org/freedesktop/DBus",
+                    Some("org.freedesktop.DBus.Peer"),
+                    "Ping",
+                    &(),
+                )
+                .await
+                .is_ok()
+            });
+
+            if !is_connected {
+                debug!("Lost connection to the system bus, reconnecting");
+                self.system_bus = None;
+            }
+        }
+    }
+
+    /// Gets the shared system bus connection,
+    /// for use by any client which talks to system services over D-Bus.
+    #[cfg(any(
+        feature = "brightness",
+        feature = "network_manager",
+        feature = "upower"
+    ))]
+    pub fn system_bus(&mut self) -> ClientResult<zbus::Connection> {
+        let client = if let Some(client) = &self.system_bus {
+            client.clone()
+        } else {
+            let client = await_sync(async { Box::pin(zbus::Connection::system()).await })?;
+            let client = Arc::new(client);
+            self.system_bus.replace(client.clone());
+            client
+        };
+
+        Ok(client)
+    }
+
+    #[cfg(feature = "upower")]
+    pub fn power_profiles(&mut self) -> ClientResult<power_profiles::PowerProfilesProxy<'static>> {
+        let client = if let Some(client) = &self.power_profiles {
+            client.clone()
+        } else {
+            let dbus = self.system_bus()?;
+            let client = await_sync(async move { power_profiles::create_proxy(&dbus).await })?;
+            self.power_profiles.replace(client.clone());
+            client
+        };
+
+        Ok(client)
+    }
+
     #[cfg(feature = "tray")]
     pub fn tray(&mut self) -> ClientResult<tray::Client> {
         let client = if let Some(client) = &self.tray {
@@ -239,11 +359,12 @@ impl Clients {
     }
 
     #[cfg(feature = "upower")]
-    pub fn upower(&mut self) -> ClientResult<zbus::fdo::PropertiesProxy<'static>> {
+    pub fn upower(&mut self) -> ClientResult<upower::UPowerProxy<'static>> {
         let client = if let Some(client) = &self.upower {
             client.clone()
         } else {
-            let client = await_sync(async { upower::create_display_proxy().await })?;
+            let dbus = self.system_bus()?;
+            let client = await_sync(async move { upower::create_upower_proxy(&dbus).await })?;
             self.upower.replace(client.clone());
             client
         };
@@ -251,6 +372,11 @@ impl Clients {
         Ok(client)
     }
 
+    #[cfg(feature = "upower")]
+    pub fn upower_devices(&mut self) -> Arc<upower::DeviceStore> {
+        self.upower_devices.get_or_insert_with(Arc::default).clone()
+    }
+
     #[cfg(feature = "volume")]
     pub fn volume(&mut self) -> Arc<volume::Client> {
         self.volume
@@ -274,6 +400,188 @@ pub trait ProvidesFallibleClient<T: ?Sized> {
     fn try_provide(&self) -> ClientResult<T>;
 }
 
+/// Types implementing this trait
+/// can create and discard a singleton client instance of type `T`,
+/// which may fail to be created.
+///
+/// This is implemented on [`Clients`] by [`register_fallible_client`].
+pub trait FallibleClient<T: ?Sized> {
+    /// Returns the singleton client instance of type `T`,
+    /// creating it if it does not exist yet.
+    fn try_get(&mut self) -> ClientResult<T>;
+
+    /// Discards the singleton client instance,
+    /// so that a new one is created on next use.
+    fn reset(&mut self);
+}
+
+/// Exponential backoff between attempts to create a client.
+///
+/// The delay starts at one second and doubles after each attempt,
+/// up to the maximum.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl Backoff {
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            delay: MIN_RETRY_DELAY,
+            max_delay: max_delay.max(MIN_RETRY_DELAY),
+        }
+    }
+
+    /// Gets the time to wait before the next attempt,
+    /// doubling it for the attempt after.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(self.max_delay);
+        delay
+    }
+
+    /// Resets the delay back to the minimum,
+    /// such as after connecting successfully.
+    pub fn reset(&mut self) {
+        self.delay = MIN_RETRY_DELAY;
+    }
+}
+
+/// A request for a client, served on the main thread.
+#[derive(Debug)]
+struct ClientRequest<T: ?Sized> {
+    /// Whether to discard any existing instance first.
+    reconnect: bool,
+    /// Whether to keep trying until the client is created.
+    retry: bool,
+    reply: oneshot::Sender<Option<Arc<T>>>,
+}
+
+/// A handle for getting the fallible client `T` from async code on any thread,
+/// such as a module controller.
+///
+/// The clients live on the GTK main thread, so each request is served there.
+/// Failed attempts are retried with [`Backoff`],
+/// so that modules recover once a service which was not running at launch appears.
+#[derive(Debug)]
+pub struct ClientRetry<T: ?Sized> {
+    tx: mpsc::Sender<ClientRequest<T>>,
+    max_delay: Duration,
+}
+
+impl<T: ?Sized> Clone for ClientRetry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            max_delay: self.max_delay,
+        }
+    }
+}
+
+impl<T> ClientRetry<T>
+where
+    T: ?Sized + Send + Sync + 'static,
+{
+    /// Creates a new handle,
+    /// waiting no longer than `max_delay` between attempts.
+    ///
+    /// This must be called from the GTK main thread.
+    pub(crate) fn new(clients: Rc<RefCell<Clients>>, max_delay: Duration) -> Self
+    where
+        Clients: FallibleClient<T>,
+    {
+        let (tx, mut rx) = mpsc::channel::<ClientRequest<T>>(8);
+
+        glib::spawn_future_local(async move {
+            while let Some(request) = rx.recv().await {
+                if request.reconnect {
+                    FallibleClient::<T>::reset(&mut *clients.borrow_mut());
+                }
+
+                let mut backoff = Backoff::new(max_delay);
+
+                let client = loop {
+                    let result = {
+                        let mut clients = clients.borrow_mut();
+                        clients.check_system_bus();
+                        FallibleClient::<T>::try_get(&mut *clients)
+                    };
+
+                    match result {
+                        Ok(client) => break Some(client),
+                        Err(err) if request.retry => {
+                            let delay = backoff.next_delay();
+                            warn!(
+                                "Failed to create {} client, retrying in {}s: {err:?}",
+                                type_name::<T>(),
+                                delay.as_secs()
+                            );
+
+                            glib::timeout_future(delay).await;
+                        }
+                        Err(err) => {
+                            debug!("Failed to create {} client: {err:?}", type_name::<T>());
+                            break None;
+                        }
+                    }
+                };
+
+                // the requester may have stopped waiting
+                let _ = request.reply.send(client);
+            }
+        });
+
+        Self { tx, max_delay }
+    }
+
+    /// Gets the client, waiting until it can be created.
+    ///
+    /// Returns `None` if the main thread has stopped.
+    pub async fn get(&self) -> Option<Arc<T>> {
+        self.request(false, true).await
+    }
+
+    /// Gets the client, making a single attempt to create it if it does not exist yet.
+    pub async fn try_get(&self) -> Option<Arc<T>> {
+        self.request(false, false).await
+    }
+
+    /// Discards the existing client and gets a new one,
+    /// waiting until it can be created.
+    ///
+    /// The shared system bus connection is also replaced if it has been lost.
+    pub async fn reconnect(&self) -> Option<Arc<T>> {
+        self.request(true, true).await
+    }
+
+    /// Like [`ClientRetry::reconnect`], but only makes a single attempt.
+    pub async fn try_reconnect(&self) -> Option<Arc<T>> {
+        self.request(true, false).await
+    }
+
+    /// Creates a new backoff with the same maximum delay,
+    /// for retrying anything else which depends on the client.
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(self.max_delay)
+    }
+
+    async fn request(&self, reconnect: bool, retry: bool) -> Option<Arc<T>> {
+        let (reply, rx) = oneshot::channel();
+
+        self.tx
+            .send(ClientRequest {
+                reconnect,
+                retry,
+                reply,
+            })
+            .await
+            .ok()?;
+
+        rx.await.ok().flatten()
+    }
+}
+
 /// Generates a `ProvidesClient` impl block on `WidgetContext`
 /// for the provided `$ty` (first argument) client type.
 ///
@@ -297,14 +605,17 @@ macro_rules! register_client {
     };
 }
 
-/// Generates a `ProvidesClient` impl block on `WidgetContext`
-/// for the provided `$ty` (first argument) client type.
+/// Generates a `ProvidesFallibleClient` impl block on `WidgetContext`
+/// for the provided `$ty` (first argument) client type,
+/// and a `FallibleClient` impl block on `Clients`
+/// so that the client can be retried with a `ClientRetry`.
 ///
 /// The implementation calls `$method` (second argument)
-/// on the `Clients` struct to obtain the client instance.
+/// on the `Clients` struct to obtain the client instance,
+/// and clears the field of the same name to discard it.
 ///
 /// # Example
-/// `register_client!(Client, clipboard);`
+/// `register_fallible_client!(Client, tray);`
 #[macro_export]
 macro_rules! register_fallible_client {
     ($ty:ty, $method:ident) => {
@@ -317,5 +628,22 @@ macro_rules! register_fallible_client {
                 self.ironbar.clients.borrow_mut().$method()
             }
         }
+
+        impl $crate::clients::FallibleClient<$ty> for $crate::clients::Clients {
+            fn try_get(&mut self) -> $crate::clients::ClientResult<$ty> {
+                self.$method()
+            }
+
+            fn reset(&mut self) {
+                self.$method = None;
+            }
+        }
     };
 }
+
+#[cfg(any(
+    feature = "brightness",
+    feature = "network_manager",
+    feature = "upower"
+))]
+register_fallible_client!(zbus::Connection, system_bus);
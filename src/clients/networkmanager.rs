@@ -62,9 +62,9 @@ trait NetworkManagerDbus {
 }
 
 impl Client {
-    async fn new() -> Result<Self> {
+    async fn new(dbus_connection: &Connection) -> Result<Self> {
         let client_state = Mutable::new(ClientState::Unknown);
-        let dbus_connection = Connection::system().await?;
+        let dbus_connection = dbus_connection.clone();
         let interface_name = InterfaceName::from_static_str(DBUS_INTERFACE)?;
         let props_proxy = PropertiesProxy::builder(&dbus_connection)
             .destination(DBUS_BUS)?
@@ -133,8 +133,8 @@ impl Client {
     }
 }
 
-pub async fn create_client() -> Result<Arc<Client>> {
-    let client = Arc::new(Client::new().await?);
+pub async fn create_client(dbus_connection: &Connection) -> Result<Arc<Client>> {
+    let client = Arc::new(Client::new(dbus_connection).await?);
     {
         let client = client.clone();
         spawn(async move {
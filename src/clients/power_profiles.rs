@@ -0,0 +1,55 @@
+use crate::clients::ClientResult;
+use crate::register_fallible_client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use zbus::proxy;
+use zbus::zvariant::OwnedValue;
+
+#[proxy(
+    interface = "org.freedesktop.UPower.PowerProfiles",
+    default_service = "org.freedesktop.UPower.PowerProfiles",
+    default_path = "/org/freedesktop/UPower/PowerProfiles"
+)]
+pub trait PowerProfiles {
+    /// ActiveProfile property
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    /// Sets the ActiveProfile property, switching to the named profile.
+    ///
+    /// This fails if the profile is not supported,
+    /// or if the caller is not permitted to change it.
+    #[zbus(property)]
+    fn set_active_profile(&self, profile: &str) -> zbus::Result<()>;
+
+    /// Profiles property
+    #[zbus(property)]
+    fn profiles(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+/// Gets the names of the profiles supported by the system,
+/// in the order reported by the daemon.
+pub async fn profile_names(proxy: &PowerProfilesProxy<'_>) -> zbus::Result<Vec<String>> {
+    let profiles = proxy.profiles().await?;
+
+    let names = profiles
+        .iter()
+        .filter_map(|profile| profile.get("Profile"))
+        .filter_map(|name| <&str>::try_from(&**name).ok())
+        .map(String::from)
+        .collect();
+
+    Ok(names)
+}
+
+/// Creates a proxy for power-profiles-daemon on the provided system bus connection.
+///
+/// This succeeds even if the daemon is not running,
+/// in which case reading any property fails.
+pub async fn create_proxy(dbus: &zbus::Connection) -> ClientResult<PowerProfilesProxy<'static>> {
+    let proxy = PowerProfilesProxy::new(dbus).await?;
+
+    Ok(Arc::new(proxy))
+}
+
+register_fallible_client!(PowerProfilesProxy<'static>, power_profiles);
@@ -0,0 +1,84 @@
+use super::{BatteryState, BatteryType, UpowerProperties};
+use color_eyre::Result;
+use std::collections::HashMap;
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::{OwnedValue, Value};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+
+/// Creates a proxy for enumerating the objects exported by BlueZ.
+pub async fn create_bluez_proxy(dbus: &zbus::Connection) -> Result<ObjectManagerProxy<'static>> {
+    let proxy = ObjectManagerProxy::builder(dbus)
+        .destination(BLUEZ_SERVICE)?
+        .path("/")?
+        .build()
+        .await?;
+
+    Ok(proxy)
+}
+
+/// Gets the battery of every Bluetooth device which reports one to BlueZ,
+/// keyed by the device's object path.
+///
+/// The properties are filled in as closely as possible to a UPower device,
+/// using the BlueZ device icon as the icon name.
+pub async fn bluez_batteries(
+    bluez: &ObjectManagerProxy<'static>,
+) -> Result<HashMap<String, UpowerProperties>> {
+    let objects = bluez.get_managed_objects().await?;
+
+    let batteries = objects
+        .into_iter()
+        .filter_map(|(path, interfaces)| {
+            let battery = interfaces.get(BATTERY_INTERFACE)?;
+            let percentage = get::<u8>(battery, "Percentage")?;
+
+            let device = interfaces.get(DEVICE_INTERFACE);
+            let name = device
+                .and_then(|device| get::<&str>(device, "Alias").or(get(device, "Name")))
+                .unwrap_or_default();
+            let icon = device
+                .and_then(|device| get::<&str>(device, "Icon"))
+                .unwrap_or("bluetooth");
+
+            let properties = UpowerProperties {
+                object_path: path.to_string(),
+                native_path: path.to_string(),
+                vendor: String::new(),
+                model: name.to_string(),
+                device_type: BatteryType::Unknown,
+                is_present: true,
+                online: false,
+                percentage: f64::from(percentage),
+                icon_name: format!("{icon}-symbolic"),
+                state: BatteryState::Unknown,
+                time_to_full: 0,
+                time_to_empty: 0,
+                energy: 0.0,
+                energy_full: 0.0,
+                energy_rate: 0.0,
+                charge_threshold_enabled: false,
+                charge_start_threshold: 0,
+                charge_end_threshold: 0,
+                is_bluez: true,
+            };
+
+            Some((properties.object_path.clone(), properties))
+        })
+        .collect();
+
+    Ok(batteries)
+}
+
+/// Gets a property of a BlueZ interface,
+/// or `None` if it is missing or of the wrong type.
+fn get<'a, T>(properties: &'a HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<&'a Value<'a>>,
+{
+    properties
+        .get(name)
+        .and_then(|value| T::try_from(&**value).ok())
+}
@@ -2,10 +2,13 @@
 /// <https://github.com/pop-os/upower-dbus/blob/main/LICENSE>
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
+use serde::Deserialize;
 use zbus::proxy;
 use zbus::zvariant::OwnedValue;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, OwnedValue)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, OwnedValue, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[repr(u32)]
 pub enum BatteryState {
     Unknown = 0,
@@ -17,7 +20,7 @@ pub enum BatteryState {
     PendingDischarge = 6,
 }
 
-#[derive(Debug, Copy, Clone, OwnedValue)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, OwnedValue)]
 #[repr(u32)]
 pub enum BatteryType {
     Unknown = 0,
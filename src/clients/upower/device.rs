@@ -0,0 +1,516 @@
+use super::{BatteryState, BatteryType};
+use color_eyre::Result;
+use futures_lite::stream::{self, Boxed, StreamExt};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use tracing::warn;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{self, OwnedObjectPath, OwnedValue, Value};
+
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// The set of device properties used by the upower module.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct UpowerProperties {
+    /// The D-Bus object path of the device, which uniquely identifies it.
+    pub object_path: String,
+    pub native_path: String,
+    pub vendor: String,
+    pub model: String,
+    #[serde(skip)]
+    pub device_type: BatteryType,
+    pub is_present: bool,
+    pub online: bool,
+    pub percentage: f64,
+    pub icon_name: String,
+    #[serde(serialize_with = "serialize_state")]
+    pub state: BatteryState,
+    pub time_to_full: i64,
+    pub time_to_empty: i64,
+    /// The energy currently stored, in Wh.
+    pub energy: f64,
+    /// The energy stored when fully charged, in Wh.
+    pub energy_full: f64,
+    /// The rate energy is being drawn or supplied at, in W.
+    pub energy_rate: f64,
+    pub charge_threshold_enabled: bool,
+    pub charge_start_threshold: u32,
+    pub charge_end_threshold: u32,
+    /// Whether the battery was read from BlueZ rather than UPower.
+    #[serde(skip)]
+    pub is_bluez: bool,
+}
+
+impl UpowerProperties {
+    /// Creates a new set of properties from a `get_all` map.
+    pub(crate) fn from_map(properties: &HashMap<String, OwnedValue>) -> Self {
+        Self {
+            object_path: String::new(),
+            native_path: get_optional_property::<&str>(properties, "NativePath")
+                .unwrap_or_default()
+                .to_string(),
+            vendor: get_property::<&str>(properties, "Vendor")
+                .unwrap_or_default()
+                .to_string(),
+            model: get_property::<&str>(properties, "Model")
+                .unwrap_or_default()
+                .to_string(),
+            device_type: get_property(properties, "Type")
+                .map_or(BatteryType::Unknown, u32_to_battery_type),
+            is_present: get_property(properties, "IsPresent").unwrap_or_default(),
+            online: get_property(properties, "Online").unwrap_or_default(),
+            percentage: get_property(properties, "Percentage").unwrap_or_default(),
+            icon_name: get_property::<&str>(properties, "IconName")
+                .unwrap_or_default()
+                .to_string(),
+            state: get_property(properties, "State").map_or(BatteryState::Unknown, parse_state),
+            time_to_full: get_property(properties, "TimeToFull").unwrap_or_default(),
+            time_to_empty: get_property(properties, "TimeToEmpty").unwrap_or_default(),
+            energy: get_property(properties, "Energy").unwrap_or_default(),
+            energy_full: get_property(properties, "EnergyFull").unwrap_or_default(),
+            energy_rate: get_property(properties, "EnergyRate").unwrap_or_default(),
+            // charge thresholds are only available on newer UPower versions
+            charge_threshold_enabled: get_optional_property(properties, "ChargeThresholdEnabled")
+                .unwrap_or_default(),
+            charge_start_threshold: get_optional_property(properties, "ChargeStartThreshold")
+                .unwrap_or_default(),
+            charge_end_threshold: get_optional_property(properties, "ChargeEndThreshold")
+                .unwrap_or_default(),
+            is_bluez: false,
+        }
+    }
+
+    /// Updates a single property from a `PropertiesChanged` signal.
+    fn update(&mut self, name: &str, value: &Value) {
+        match name {
+            "IsPresent" => {
+                if let Some(is_present) = downcast_property(name, value) {
+                    self.is_present = is_present;
+                }
+            }
+            "Online" => {
+                if let Some(online) = downcast_property(name, value) {
+                    self.online = online;
+                }
+            }
+            "Percentage" => {
+                if let Some(percentage) = downcast_property(name, value) {
+                    self.percentage = percentage;
+                }
+            }
+            "IconName" => {
+                if let Some(icon_name) = downcast_property::<&str>(name, value) {
+                    self.icon_name = icon_name.to_string();
+                }
+            }
+            "State" => {
+                if let Some(state) = downcast_property(name, value) {
+                    self.state = parse_state(state);
+                }
+            }
+            "TimeToFull" => {
+                if let Some(time_to_full) = downcast_property(name, value) {
+                    self.time_to_full = time_to_full;
+                }
+            }
+            "TimeToEmpty" => {
+                if let Some(time_to_empty) = downcast_property(name, value) {
+                    self.time_to_empty = time_to_empty;
+                }
+            }
+            "Energy" => {
+                if let Some(energy) = downcast_property(name, value) {
+                    self.energy = energy;
+                }
+            }
+            "EnergyFull" => {
+                if let Some(energy_full) = downcast_property(name, value) {
+                    self.energy_full = energy_full;
+                }
+            }
+            "EnergyRate" => {
+                if let Some(energy_rate) = downcast_property(name, value) {
+                    self.energy_rate = energy_rate;
+                }
+            }
+            "ChargeThresholdEnabled" => {
+                if let Some(enabled) = downcast_property(name, value) {
+                    self.charge_threshold_enabled = enabled;
+                }
+            }
+            "ChargeStartThreshold" => {
+                if let Some(threshold) = downcast_property(name, value) {
+                    self.charge_start_threshold = threshold;
+                }
+            }
+            "ChargeEndThreshold" => {
+                if let Some(threshold) = downcast_property(name, value) {
+                    self.charge_end_threshold = threshold;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Gets the percentage charging stops at,
+    /// or `None` if no charge limit is active.
+    pub fn charge_limit(&self) -> Option<u32> {
+        (self.charge_threshold_enabled && self.charge_end_threshold > 0)
+            .then_some(self.charge_end_threshold)
+    }
+
+    /// Applies a batch of changes from a `PropertiesChanged` signal.
+    ///
+    /// UPower may update the state and time estimates in separate batches,
+    /// so when the state changes, any estimate not refreshed in the same batch
+    /// is cleared rather than left over from the previous state.
+    fn apply_changes(&mut self, changes: &HashMap<&str, Value>) {
+        let previous_state = self.state;
+
+        for (name, value) in changes {
+            self.update(name, value);
+        }
+
+        if self.state != previous_state {
+            if !changes.contains_key("TimeToFull") {
+                self.time_to_full = 0;
+            }
+
+            if !changes.contains_key("TimeToEmpty") {
+                self.time_to_empty = 0;
+            }
+        }
+    }
+
+    /// Gets a human-readable name for the device,
+    /// using its model where available.
+    pub fn name(&self) -> &str {
+        if self.model.is_empty() {
+            &self.native_path
+        } else {
+            &self.model
+        }
+    }
+
+    /// Gets the device's vendor and model,
+    /// falling back to its name if neither is available.
+    pub fn title(&self) -> String {
+        let title = format!("{} {}", self.vendor, self.model);
+        let title = title.trim();
+
+        if title.is_empty() {
+            self.name().to_string()
+        } else {
+            title.to_string()
+        }
+    }
+}
+
+/// A single UPower device,
+/// exposing its properties as parsed values.
+#[derive(Debug, Clone)]
+pub struct Device {
+    proxy: PropertiesProxy<'static>,
+    interface_name: InterfaceName<'static>,
+}
+
+impl Device {
+    pub(super) fn new(proxy: PropertiesProxy<'static>) -> Self {
+        Self {
+            proxy,
+            interface_name: InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE),
+        }
+    }
+
+    /// Gets the object path of the device.
+    pub fn path(&self) -> OwnedObjectPath {
+        self.proxy.inner().path().to_owned().into()
+    }
+
+    /// Gets the current value of every tracked property.
+    ///
+    /// Some virtual devices do not report a native path,
+    /// so the object path is used in its place.
+    pub async fn properties(&self) -> Result<UpowerProperties> {
+        let properties = self.proxy.get_all(self.interface_name.clone()).await?;
+        let mut properties = UpowerProperties::from_map(&properties);
+        properties.object_path = self.path().to_string();
+
+        if properties.native_path.is_empty() {
+            properties.native_path.clone_from(&properties.object_path);
+        }
+
+        Ok(properties)
+    }
+
+    pub async fn device_type(&self) -> Result<BatteryType> {
+        self.get("Type").await.map(u32_to_battery_type)
+    }
+
+    pub async fn native_path(&self) -> Result<String> {
+        self.get("NativePath").await
+    }
+
+    pub async fn model(&self) -> Result<String> {
+        self.get("Model").await
+    }
+
+    pub async fn is_present(&self) -> Result<bool> {
+        self.get("IsPresent").await
+    }
+
+    pub async fn percentage(&self) -> Result<f64> {
+        self.get("Percentage").await
+    }
+
+    pub async fn icon_name(&self) -> Result<String> {
+        self.get("IconName").await
+    }
+
+    pub async fn state(&self) -> Result<BatteryState> {
+        self.get("State").await.map(parse_state)
+    }
+
+    pub async fn time_to_full(&self) -> Result<i64> {
+        self.get("TimeToFull").await
+    }
+
+    pub async fn time_to_empty(&self) -> Result<i64> {
+        self.get("TimeToEmpty").await
+    }
+
+    /// Re-fetches a single property, such as one which has been invalidated,
+    /// updating its value in `properties`.
+    async fn refresh_property(&self, properties: &mut UpowerProperties, name: &str) -> Result<()> {
+        match name {
+            "NativePath" => properties.native_path = self.native_path().await?,
+            "Model" => properties.model = self.model().await?,
+            "IsPresent" => properties.is_present = self.is_present().await?,
+            "Percentage" => properties.percentage = self.percentage().await?,
+            "IconName" => properties.icon_name = self.icon_name().await?,
+            "State" => properties.state = self.state().await?,
+            "TimeToFull" => properties.time_to_full = self.time_to_full().await?,
+            "TimeToEmpty" => properties.time_to_empty = self.time_to_empty().await?,
+            _ => {
+                let value = self.proxy.get(self.interface_name.clone(), name).await?;
+                properties.update(name, &value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a stream of the device's properties.
+    ///
+    /// The first item is the current set of properties,
+    /// followed by an updated set each time any of them change.
+    ///
+    /// The stream ends when the underlying bus connection closes.
+    pub async fn receive_properties(&self) -> Result<Boxed<UpowerProperties>> {
+        // subscribe first so that no changes are missed between fetching and listening
+        let signals = self.proxy.receive_properties_changed().await?;
+        let properties = self.properties().await?;
+
+        let changes = stream::unfold(
+            (properties.clone(), signals, self.clone()),
+            |(mut properties, mut signals, device)| async move {
+                while let Some(signal) = signals.next().await {
+                    let Ok(args) = signal.args() else {
+                        warn!("Received invalid upower signal arguments");
+                        continue;
+                    };
+
+                    if args.interface_name != device.interface_name {
+                        continue;
+                    }
+
+                    properties.apply_changes(&args.changed_properties);
+
+                    // invalidated properties are sent without a value,
+                    // so must be fetched separately
+                    for name in args.invalidated_properties.iter() {
+                        if let Err(err) = device.refresh_property(&mut properties, name).await {
+                            warn!("Failed to get invalidated upower property '{name}': {err}");
+                        }
+                    }
+
+                    return Some((properties.clone(), (properties, signals, device)));
+                }
+
+                None
+            },
+        );
+
+        Ok(stream::once(properties).chain(changes).boxed())
+    }
+
+    async fn get<T>(&self, name: &str) -> Result<T>
+    where
+        T: TryFrom<OwnedValue>,
+        <T as TryFrom<OwnedValue>>::Error: Into<zvariant::Error>,
+    {
+        let value = self.proxy.get(self.interface_name.clone(), name).await?;
+        T::try_from(value).map_err(|err| err.into().into())
+    }
+}
+
+/// Attempts to downcast a property value to `T`,
+/// logging a warning if it is of an unexpected type.
+fn downcast_property<'a, T>(name: &str, value: &'a Value<'a>) -> Option<T>
+where
+    T: TryFrom<&'a Value<'a>>,
+    <T as TryFrom<&'a Value<'a>>>::Error: Into<zvariant::Error>,
+{
+    value
+        .downcast_ref::<T>()
+        .inspect_err(|err| warn!("Unexpected type for upower property '{name}': {err}"))
+        .ok()
+}
+
+/// Attempts to get a property from a `get_all` map as type `T`,
+/// logging a warning if it is missing or of an unexpected type.
+fn get_property<'a, T>(properties: &'a HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<&'a Value<'a>>,
+    <T as TryFrom<&'a Value<'a>>>::Error: Into<zvariant::Error>,
+{
+    let Some(value) = properties.get(name) else {
+        warn!("Missing upower property '{name}'");
+        return None;
+    };
+
+    downcast_property(name, value)
+}
+
+/// Like [`get_property`], but without warning if the property is missing.
+fn get_optional_property<'a, T>(
+    properties: &'a HashMap<String, OwnedValue>,
+    name: &str,
+) -> Option<T>
+where
+    T: TryFrom<&'a Value<'a>>,
+    <T as TryFrom<&'a Value<'a>>>::Error: Into<zvariant::Error>,
+{
+    properties
+        .get(name)
+        .and_then(|value| downcast_property(name, value))
+}
+
+pub const fn battery_state_to_string(state: BatteryState) -> &'static str {
+    match state {
+        BatteryState::Unknown => "Unknown",
+        BatteryState::Charging => "Charging",
+        BatteryState::Discharging => "Discharging",
+        BatteryState::Empty => "Empty",
+        BatteryState::FullyCharged => "Fully charged",
+        BatteryState::PendingCharge => "Pending charge",
+        BatteryState::PendingDischarge => "Pending discharge",
+    }
+}
+
+fn serialize_state<S: Serializer>(state: &BatteryState, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(battery_state_to_string(*state))
+}
+
+/// Parses a raw battery state,
+/// falling back to `Unknown` for unrecognised values.
+fn parse_state(state: u32) -> BatteryState {
+    u32_to_battery_state(state).unwrap_or_else(|state| {
+        warn!("Unknown upower battery state: {state}");
+        BatteryState::Unknown
+    })
+}
+
+const fn u32_to_battery_state(number: u32) -> Result<BatteryState, u32> {
+    if number == (BatteryState::Unknown as u32) {
+        Ok(BatteryState::Unknown)
+    } else if number == (BatteryState::Charging as u32) {
+        Ok(BatteryState::Charging)
+    } else if number == (BatteryState::Discharging as u32) {
+        Ok(BatteryState::Discharging)
+    } else if number == (BatteryState::Empty as u32) {
+        Ok(BatteryState::Empty)
+    } else if number == (BatteryState::FullyCharged as u32) {
+        Ok(BatteryState::FullyCharged)
+    } else if number == (BatteryState::PendingCharge as u32) {
+        Ok(BatteryState::PendingCharge)
+    } else if number == (BatteryState::PendingDischarge as u32) {
+        Ok(BatteryState::PendingDischarge)
+    } else {
+        Err(number)
+    }
+}
+
+const fn u32_to_battery_type(number: u32) -> BatteryType {
+    match number {
+        1 => BatteryType::LinePower,
+        2 => BatteryType::Battery,
+        3 => BatteryType::Ups,
+        4 => BatteryType::Monitor,
+        5 => BatteryType::Mouse,
+        6 => BatteryType::Keyboard,
+        7 => BatteryType::Pda,
+        8 => BatteryType::Phone,
+        _ => BatteryType::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_properties_use_defaults() {
+        let mut map = HashMap::new();
+        map.insert(
+            String::from("Percentage"),
+            OwnedValue::try_from(Value::from(42.0)).unwrap(),
+        );
+
+        let properties = UpowerProperties::from_map(&map);
+        assert_eq!(properties.percentage, 42.0);
+        assert_eq!(properties.state, BatteryState::Unknown);
+        assert_eq!(properties.device_type, BatteryType::Unknown);
+    }
+
+    #[test]
+    fn unknown_state_falls_back() {
+        let mut properties = UpowerProperties::from_map(&HashMap::new());
+
+        properties.update("State", &Value::from(1u32));
+        assert_eq!(properties.state, BatteryState::Charging);
+
+        properties.update("State", &Value::from(99u32));
+        assert_eq!(properties.state, BatteryState::Unknown);
+    }
+
+    #[test]
+    fn state_change_clears_stale_estimates() {
+        let mut properties = UpowerProperties::from_map(&HashMap::new());
+        properties.time_to_full = 3600;
+
+        let changes = HashMap::from([
+            ("State", Value::from(2u32)),
+            ("TimeToEmpty", Value::from(7200i64)),
+        ]);
+        properties.apply_changes(&changes);
+
+        assert_eq!(properties.state, BatteryState::Discharging);
+        assert_eq!(properties.time_to_full, 0);
+        assert_eq!(properties.time_to_empty, 7200);
+    }
+
+    #[test]
+    fn title_falls_back_to_name() {
+        let mut properties = UpowerProperties::from_map(&HashMap::new());
+        properties.native_path = String::from("BAT0");
+        assert_eq!(properties.title(), "BAT0");
+
+        properties.model = String::from("MX Master");
+        assert_eq!(properties.title(), "MX Master");
+
+        properties.vendor = String::from("Logitech");
+        assert_eq!(properties.title(), "Logitech MX Master");
+    }
+}
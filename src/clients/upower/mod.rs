@@ -1,33 +1,446 @@
+mod bluez;
 mod dbus;
+mod device;
 
+use crate::channels::SyncSenderExt;
 use crate::clients::ClientResult;
-use crate::register_fallible_client;
-use dbus::UPowerProxy;
-use std::sync::Arc;
+use crate::{Ironbar, lock, register_client, register_fallible_client, spawn};
+use color_eyre::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::debug;
 use zbus::fdo::PropertiesProxy;
 use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
 
-pub use dbus::BatteryState;
+pub use bluez::{bluez_batteries, create_bluez_proxy};
+pub use dbus::{BatteryState, BatteryType, UPowerProxy};
+pub use device::{Device, UpowerProperties, battery_state_to_string};
 
-pub async fn create_display_proxy() -> ClientResult<PropertiesProxy<'static>> {
-    let dbus = Box::pin(zbus::Connection::system()).await?;
+/// Creates a proxy for the UPower service on the provided system bus connection.
+pub async fn create_upower_proxy(dbus: &zbus::Connection) -> ClientResult<UPowerProxy<'static>> {
+    let proxy = UPowerProxy::new(dbus).await?;
 
-    let device_proxy = UPowerProxy::new(&dbus).await?;
+    Ok(Arc::new(proxy))
+}
+
+/// Gets the object path of the composite display device.
+pub async fn display_device_path(upower: &UPowerProxy<'static>) -> Result<OwnedObjectPath> {
+    let display_device = upower.get_display_device().await?;
+    Ok(display_device.inner().path().to_owned().into())
+}
+
+/// Finds the first device whose `NativePath` or `Model`
+/// contains the provided query string.
+///
+/// Returns `None` if no device matches.
+pub async fn find_device_path(
+    upower: &UPowerProxy<'static>,
+    query: &str,
+) -> Result<Option<OwnedObjectPath>> {
+    for path in upower.enumerate_devices().await? {
+        let device = create_device(upower, path.clone()).await?;
+
+        let native_path = device.native_path().await.unwrap_or_default();
+        let model = device.model().await.unwrap_or_default();
 
-    let display_device = device_proxy.get_display_device().await?;
+        debug!("checking device {path} (native path: '{native_path}', model: '{model}')");
 
-    let path = display_device.inner().path();
+        if native_path.contains(query) || model.contains(query) {
+            return Ok(Some(path));
+        }
+    }
 
-    let proxy = PropertiesProxy::builder(&dbus)
-        .destination("org.freedesktop.UPower")
-        .expect("failed to set proxy destination address")
-        .path(path)
-        .expect("failed to set proxy path")
+    Ok(None)
+}
+
+/// Creates a device for the provided object path.
+pub async fn create_device(upower: &UPowerProxy<'static>, path: OwnedObjectPath) -> Result<Device> {
+    let proxy = PropertiesProxy::builder(upower.inner().connection())
+        .destination("org.freedesktop.UPower")?
+        .path(path)?
         .cache_properties(CacheProperties::No)
         .build()
         .await?;
 
-    Ok(Arc::new(proxy))
+    Ok(Device::new(proxy))
+}
+
+/// Finds the first line power device, such as an AC adapter.
+///
+/// Returns `None` if the system has no line power device.
+pub async fn find_line_power(upower: &UPowerProxy<'static>) -> Result<Option<Device>> {
+    for path in upower.enumerate_devices().await? {
+        let device = create_device(upower, path).await?;
+
+        if device.device_type().await? == BatteryType::LinePower {
+            return Ok(Some(device));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Creates a device for the provided object path,
+/// if it is a battery.
+///
+/// Returns `None` for any other type of device, such as line power or a UPS.
+pub async fn create_battery(
+    upower: &UPowerProxy<'static>,
+    path: OwnedObjectPath,
+) -> Result<Option<Device>> {
+    let device = create_device(upower, path).await?;
+    let is_battery = device.device_type().await? == BatteryType::Battery;
+
+    Ok(is_battery.then_some(device))
+}
+
+/// Creates a device for each device to track.
+///
+/// If `use_display_device` is `true`, only the composite display device is used.
+/// Otherwise, every battery known to UPower is enumerated.
+///
+/// The devices are created concurrently, but returned in enumeration order.
+pub async fn create_devices(
+    upower: &UPowerProxy<'static>,
+    use_display_device: bool,
+) -> Result<Vec<Device>> {
+    if use_display_device {
+        let path = display_device_path(upower).await?;
+        return Ok(vec![create_device(upower, path).await?]);
+    }
+
+    let handles = upower
+        .enumerate_devices()
+        .await?
+        .into_iter()
+        .map(|path| {
+            let upower = upower.clone();
+            spawn(async move { create_battery(&upower, path).await })
+        })
+        .collect::<Vec<_>>();
+
+    let mut devices = Vec::with_capacity(handles.len());
+    for handle in handles {
+        if let Some(device) = handle.await?? {
+            devices.push(device);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// A change to the contents of the [`DeviceStore`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoreEvent {
+    /// A device was added, or its properties changed.
+    Device(UpowerProperties),
+    /// A device was removed.
+    Remove { object_path: String },
+    /// Whether the system is on line power changed.
+    AcOnline { online: bool },
+    /// The active power profile changed.
+    PowerProfile { profile: String },
+}
+
+/// The latest properties of every device tracked by any upower module,
+/// keyed by object path.
+///
+/// This allows device state to be queried over IPC.
+/// Every change is also broadcast, so that it can be streamed to IPC subscribers.
+///
+/// Devices are added through a [`StoreHandle`] for each watcher,
+/// and are kept until no handle tracks them.
+#[derive(Debug)]
+pub struct DeviceStore {
+    devices: Mutex<HashMap<String, StoredDevice>>,
+    ac_online: Mutex<Option<bool>>,
+    power_profile: Mutex<Option<String>>,
+    tx: broadcast::Sender<StoreEvent>,
+    _rx: broadcast::Receiver<StoreEvent>,
+}
+
+impl Default for DeviceStore {
+    fn default() -> Self {
+        let (tx, rx) = broadcast::channel(32);
+
+        Self {
+            devices: Mutex::default(),
+            ac_online: Mutex::default(),
+            power_profile: Mutex::default(),
+            tx,
+            _rx: rx,
+        }
+    }
+}
+
+/// A device in the [`DeviceStore`],
+/// along with the handles which track it.
+#[derive(Debug)]
+struct StoredDevice {
+    properties: UpowerProperties,
+    owners: HashSet<usize>,
+}
+
+impl DeviceStore {
+    /// Creates a handle for adding devices to the store.
+    ///
+    /// Any devices which only this handle tracks are removed once it is dropped.
+    pub fn handle(self: &Arc<Self>) -> StoreHandle {
+        StoreHandle {
+            store: self.clone(),
+            owner: Ironbar::unique_id(),
+        }
+    }
+
+    /// Inserts or updates a device's properties,
+    /// tracking it for `owner`.
+    fn insert(&self, owner: usize, properties: UpowerProperties) {
+        let mut devices = lock!(self.devices);
+
+        let is_changed = match devices.get_mut(&properties.object_path) {
+            Some(device) => {
+                device.owners.insert(owner);
+
+                let is_changed = device.properties != properties;
+                device.properties = properties.clone();
+                is_changed
+            }
+            None => {
+                devices.insert(
+                    properties.object_path.clone(),
+                    StoredDevice {
+                        properties: properties.clone(),
+                        owners: HashSet::from([owner]),
+                    },
+                );
+                true
+            }
+        };
+
+        drop(devices);
+
+        if is_changed {
+            self.tx.send_expect(StoreEvent::Device(properties));
+        }
+    }
+
+    /// Stops tracking a device for `owner`,
+    /// removing it once no other owner tracks it.
+    fn remove(&self, owner: usize, object_path: &str) {
+        let mut devices = lock!(self.devices);
+
+        let Some(device) = devices.get_mut(object_path) else {
+            return;
+        };
+
+        device.owners.remove(&owner);
+        if !device.owners.is_empty() {
+            return;
+        }
+
+        devices.remove(object_path);
+        drop(devices);
+
+        self.tx.send_expect(StoreEvent::Remove {
+            object_path: object_path.to_string(),
+        });
+    }
+
+    /// Stops tracking every device for `owner`,
+    /// removing any devices which no other owner tracks.
+    fn release(&self, owner: usize) {
+        let mut removed = vec![];
+
+        lock!(self.devices).retain(|object_path, device| {
+            device.owners.remove(&owner);
+
+            let is_tracked = !device.owners.is_empty();
+            if !is_tracked {
+                removed.push(object_path.clone());
+            }
+
+            is_tracked
+        });
+
+        for object_path in removed {
+            self.tx.send_expect(StoreEvent::Remove { object_path });
+        }
+    }
+
+    /// Removes any BlueZ batteries which UPower already tracks.
+    ///
+    /// The batteries are keyed by their BlueZ object path,
+    /// which UPower reports as the native path of Bluetooth devices.
+    pub fn retain_untracked(&self, batteries: &mut HashMap<String, UpowerProperties>) {
+        let devices = lock!(self.devices);
+
+        batteries.retain(|path, _| {
+            !devices
+                .values()
+                .any(|device| device.properties.native_path == *path)
+        });
+    }
+
+    /// Gets the properties of all known devices.
+    pub fn get_all(&self) -> HashMap<String, UpowerProperties> {
+        lock!(self.devices)
+            .iter()
+            .map(|(object_path, device)| (object_path.clone(), device.properties.clone()))
+            .collect()
+    }
+
+    /// Sets whether the line power device is online.
+    pub fn set_ac_online(&self, online: bool) {
+        let previous = lock!(self.ac_online).replace(online);
+
+        if previous != Some(online) {
+            self.tx.send_expect(StoreEvent::AcOnline { online });
+        }
+    }
+
+    /// Gets whether the line power device is online,
+    /// or `None` if there is no known line power device.
+    pub fn ac_online(&self) -> Option<bool> {
+        *lock!(self.ac_online)
+    }
+
+    /// Sets the active power profile.
+    pub fn set_power_profile(&self, profile: String) {
+        let previous = lock!(self.power_profile).replace(profile.clone());
+
+        if previous.as_ref() != Some(&profile) {
+            self.tx.send_expect(StoreEvent::PowerProfile { profile });
+        }
+    }
+
+    /// Gets the active power profile,
+    /// or `None` if power-profiles-daemon is not available.
+    pub fn power_profile(&self) -> Option<String> {
+        lock!(self.power_profile).clone()
+    }
+
+    /// Subscribes to changes to the store.
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.tx.subscribe()
+    }
 }
 
-register_fallible_client!(PropertiesProxy<'static>, upower);
+/// A handle for a single watcher to update the [`DeviceStore`].
+///
+/// The same device may be tracked by several handles,
+/// such as when the upower module is on more than one bar.
+/// A device is only removed from the store once no handle tracks it,
+/// which includes when the handles are dropped.
+#[derive(Debug)]
+pub struct StoreHandle {
+    store: Arc<DeviceStore>,
+    owner: usize,
+}
+
+impl StoreHandle {
+    /// Inserts or updates a device's properties.
+    pub fn insert(&self, properties: UpowerProperties) {
+        self.store.insert(self.owner, properties);
+    }
+
+    /// Stops tracking a device,
+    /// removing it from the store if no other handle tracks it.
+    pub fn remove(&self, object_path: &str) {
+        self.store.remove(self.owner, object_path);
+    }
+
+    /// See [`DeviceStore::retain_untracked`].
+    pub fn retain_untracked(&self, batteries: &mut HashMap<String, UpowerProperties>) {
+        self.store.retain_untracked(batteries);
+    }
+
+    /// See [`DeviceStore::set_ac_online`].
+    pub fn set_ac_online(&self, online: bool) {
+        self.store.set_ac_online(online);
+    }
+
+    /// See [`DeviceStore::set_power_profile`].
+    pub fn set_power_profile(&self, profile: String) {
+        self.store.set_power_profile(profile);
+    }
+}
+
+impl Drop for StoreHandle {
+    fn drop(&mut self) {
+        self.store.release(self.owner);
+    }
+}
+
+register_fallible_client!(UPowerProxy<'static>, upower);
+register_client!(DeviceStore, upower_devices);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_with_same_native_path_are_kept() {
+        let store = Arc::new(DeviceStore::default());
+        let handle = store.handle();
+
+        for path in [
+            "/org/freedesktop/UPower/devices/a",
+            "/org/freedesktop/UPower/devices/b",
+        ] {
+            let mut properties = UpowerProperties::from_map(&HashMap::new());
+            properties.object_path = path.to_string();
+            properties.native_path = String::from("BAT0");
+            handle.insert(properties);
+        }
+
+        assert_eq!(store.get_all().len(), 2);
+    }
+
+    #[test]
+    fn tracked_bluez_batteries_are_removed() {
+        let bluez_path = "/org/bluez/hci0/dev_00_11_22_33_44_55";
+
+        let store = Arc::new(DeviceStore::default());
+        let handle = store.handle();
+
+        let mut properties = UpowerProperties::from_map(&HashMap::new());
+        properties.object_path = String::from("/org/freedesktop/UPower/devices/headset_dev_00");
+        properties.native_path = bluez_path.to_string();
+        handle.insert(properties);
+
+        let mut batteries = HashMap::new();
+        for path in [bluez_path, "/org/bluez/hci0/dev_66_77_88_99_AA_BB"] {
+            let mut properties = UpowerProperties::from_map(&HashMap::new());
+            properties.object_path = path.to_string();
+            batteries.insert(path.to_string(), properties);
+        }
+
+        store.retain_untracked(&mut batteries);
+
+        assert_eq!(batteries.len(), 1);
+        assert!(!batteries.contains_key(bluez_path));
+    }
+
+    #[test]
+    fn devices_are_removed_with_last_handle() {
+        let store = Arc::new(DeviceStore::default());
+        let first = store.handle();
+        let second = store.handle();
+
+        for (handle, path) in [(&first, "a"), (&second, "a"), (&second, "b")] {
+            let mut properties = UpowerProperties::from_map(&HashMap::new());
+            properties.object_path = path.to_string();
+            handle.insert(properties);
+        }
+
+        drop(second);
+        assert_eq!(store.get_all().into_keys().collect::<Vec<_>>(), ["a"]);
+
+        first.remove("a");
+        assert!(store.get_all().is_empty());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::dynamic_value::{DynamicBool, dynamic_string};
+use crate::gtk_helpers::IronbarGtkExt;
 use crate::script::{Script, ScriptInput};
 use glib::Propagation;
 use gtk::gdk::ScrollDirection;
 use gtk::prelude::*;
-use gtk::{EventBox, Justification, Orientation, Revealer, RevealerTransitionType};
+use gtk::{Align, EventBox, Justification, Orientation, Revealer, RevealerTransitionType};
 use serde::Deserialize;
 use tracing::trace;
 
@@ -44,6 +45,15 @@ pub struct CommonConfig {
     /// **Default**: `null`
     pub tooltip: Option<String>,
 
+    /// The name of the cursor to show while hovering over the module,
+    /// such as `pointer` or `text`.
+    ///
+    /// Accepts any [CSS cursor name](https://developer.mozilla.org/en-US/docs/Web/CSS/cursor).
+    /// Some modules, such as clickable buttons, may show their own cursor over part of the module.
+    ///
+    /// **Default**: `null`
+    pub cursor: Option<String>,
+
     /// Shows the module only if the dynamic boolean evaluates to true.
     ///
     /// This allows for modules to be dynamically shown or hidden
@@ -209,6 +219,32 @@ pub enum ModuleJustification {
     Fill,
 }
 
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ModuleAlignment {
+    /// Align widget to the start (left for horizontal, top for vertical).
+    Start,
+    /// Align widget to the center.
+    Center,
+    /// Align widget to the end (right for horizontal, bottom for vertical).
+    End,
+    /// Stretch widget to fill available space.
+    #[default]
+    Fill,
+}
+
+impl From<ModuleAlignment> for Align {
+    fn from(align: ModuleAlignment) -> Self {
+        match align {
+            ModuleAlignment::Start => Self::Start,
+            ModuleAlignment::Center => Self::Center,
+            ModuleAlignment::End => Self::End,
+            ModuleAlignment::Fill => Self::Fill,
+        }
+    }
+}
+
 impl From<ModuleJustification> for Justification {
     fn from(o: ModuleJustification) -> Self {
         match o {
@@ -300,6 +336,10 @@ impl CommonConfig {
         install_oneshot!(self.on_mouse_enter, connect_enter_notify_event);
         install_oneshot!(self.on_mouse_exit, connect_leave_notify_event);
 
+        if let Some(cursor) = self.cursor {
+            container.set_cursor_name(&cursor);
+        }
+
         if let Some(tooltip) = self.tooltip {
             dynamic_string(&tooltip, container, move |container, string| {
                 container.set_tooltip_text(Some(&string));
@@ -1,5 +1,6 @@
-use crate::config::{ModuleJustification, ModuleOrientation};
+use crate::config::{ModuleAlignment, ModuleJustification, ModuleOrientation};
 use crate::modules::ModuleInfo;
+use gtk::prelude::*;
 use serde::Deserialize;
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -20,6 +21,27 @@ pub struct LayoutConfig {
     /// **Default**: `left`
     #[serde(default)]
     pub justify: ModuleJustification,
+
+    /// The horizontal alignment of the widget within the space given to it by the bar.
+    ///
+    /// **Valid options**: `start`, `center`, `end`, `fill`
+    /// <br>
+    /// **Default**: `fill`
+    #[serde(default)]
+    pub halign: ModuleAlignment,
+
+    /// The vertical alignment of the widget within the space given to it by the bar.
+    ///
+    /// **Valid options**: `start`, `center`, `end`, `fill`
+    /// <br>
+    /// **Default**: `fill`
+    #[serde(default)]
+    pub valign: ModuleAlignment,
+
+    /// The space between each item in the widget, in pixels.
+    ///
+    /// **Default**: Depends on the module, usually `0`
+    spacing: Option<i32>,
 }
 
 impl LayoutConfig {
@@ -32,4 +54,16 @@ impl LayoutConfig {
         self.orientation
             .map_or(info.bar_position.angle(), ModuleOrientation::to_angle)
     }
+
+    /// Aligns the widget using the configured `halign` and `valign`.
+    pub fn set_alignment(&self, widget: &impl IsA<gtk::Widget>) {
+        widget.set_halign(self.halign.into());
+        widget.set_valign(self.valign.into());
+    }
+
+    /// Gets the configured spacing,
+    /// or the module's `default` if not set.
+    pub fn spacing(&self, default: i32) -> i32 {
+        self.spacing.unwrap_or(default)
+    }
 }
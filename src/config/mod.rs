@@ -5,6 +5,8 @@ mod truncate;
 
 #[cfg(feature = "bindmode")]
 use crate::modules::bindmode::Bindmode;
+#[cfg(feature = "brightness")]
+use crate::modules::brightness::BrightnessModule;
 #[cfg(feature = "cairo")]
 use crate::modules::cairo::CairoModule;
 #[cfg(feature = "clipboard")]
@@ -47,11 +49,14 @@ use cfg_if::cfg_if;
 use color_eyre::Result;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
 
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 
-pub use self::common::{CommonConfig, ModuleJustification, ModuleOrientation, TransitionType};
+pub use self::common::{
+    CommonConfig, ModuleAlignment, ModuleJustification, ModuleOrientation, TransitionType,
+};
 pub use self::layout::LayoutConfig;
 pub use self::truncate::{EllipsizeMode, TruncateMode};
 
@@ -61,6 +66,8 @@ pub use self::truncate::{EllipsizeMode, TruncateMode};
 pub enum ModuleConfig {
     #[cfg(feature = "bindmode")]
     Bindmode(Box<Bindmode>),
+    #[cfg(feature = "brightness")]
+    Brightness(Box<BrightnessModule>),
     #[cfg(feature = "cairo")]
     Cairo(Box<CairoModule>),
     #[cfg(feature = "clipboard")]
@@ -115,6 +122,8 @@ impl ModuleConfig {
         match self {
             #[cfg(feature = "bindmode")]
             Self::Bindmode(module) => create!(module),
+            #[cfg(feature = "brightness")]
+            Self::Brightness(module) => create!(module),
             #[cfg(feature = "cairo")]
             Self::Cairo(module) => create!(module),
             #[cfg(feature = "clipboard")]
@@ -414,6 +423,20 @@ pub struct Config {
     /// **Default**: `{}`
     #[serde(default)]
     pub icon_overrides: HashMap<String, String>,
+
+    /// The longest time to wait between attempts to connect to a service, in seconds,
+    /// such as when UPower starts after Ironbar or restarts.
+    /// The wait starts at one second and doubles after each failed attempt.
+    ///
+    /// **Default**: `60`
+    pub max_retry_delay: Option<u64>,
+}
+
+impl Config {
+    /// Gets the longest time to wait between attempts to connect to a service.
+    pub fn max_retry_delay(&self) -> Duration {
+        Duration::from_secs(self.max_retry_delay.unwrap_or(60))
+    }
 }
 
 const fn default_layer() -> gtk_layer_shell::Layer {
@@ -155,10 +155,18 @@ fn parse_script(chars: &[char]) -> (DynamicStringSegment, usize) {
 fn parse_variable(chars: &[char]) -> (DynamicStringSegment, usize) {
     const SKIP_HASH: usize = 1;
 
+    let is_name_char = |c: &char| c.is_ascii_alphanumeric() || c == &'_' || c == &'-';
+
+    // a `.` separates a module's name from its variable, such as `#battery.percentage`,
+    // but is treated as text if nothing follows it
     let str = chars
         .iter()
+        .enumerate()
         .skip(1)
-        .take_while(|&c| c.is_ascii_alphanumeric() || c == &'_' || c == &'-')
+        .take_while(|&(i, c)| {
+            is_name_char(c) || (c == &'.' && chars.get(i + 1).is_some_and(is_name_char))
+        })
+        .map(|(_, c)| c)
         .collect::<String>();
 
     let len = str.chars().count() + SKIP_HASH;
@@ -230,6 +238,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dotted_variable() {
+        const INPUT: &str = "#battery.percentage.";
+        let (tokens, _) = parse_input(INPUT);
+
+        assert_eq!(tokens.len(), 2);
+        assert!(
+            matches!(&tokens[0], DynamicStringSegment::Variable(name) if name.to_string() == "battery.percentage")
+        );
+        assert!(matches!(&tokens[1], DynamicStringSegment::Static(str) if str == "."));
+    }
+
     #[test]
     fn test_static_script() {
         const INPUT: &str = "hello {{echo world}}";
@@ -1,8 +1,10 @@
 use crate::config::TruncateMode;
 use glib::{IsA, markup_escape_text};
+use gtk::gdk::Cursor;
 use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
 use gtk::{Label, Orientation, Widget};
+use tracing::warn;
 
 /// Represents a widget's size
 /// and location relative to the bar's start edge.
@@ -24,6 +26,13 @@ pub trait IronbarGtkExt {
     fn remove_class(&self, class: &str);
     /// Gets the geometry for the widget
     fn geometry(&self, orientation: Orientation) -> WidgetGeometry;
+    /// Sets the cursor shown while hovering over the widget,
+    /// using its CSS name.
+    ///
+    /// The cursor is applied once the widget is realized.
+    fn set_cursor_name(&self, name: &str);
+    /// Sets the name announced for the widget by screen readers.
+    fn set_accessible_name(&self, name: &str);
 
     /// Gets a data tag on a widget, if it exists.
     fn get_tag<V: 'static>(&self, key: &str) -> Option<&V>;
@@ -75,6 +84,28 @@ impl<W: IsA<Widget>> IronbarGtkExt for W {
         }
     }
 
+    fn set_cursor_name(&self, name: &str) {
+        let name = name.to_string();
+        self.connect_realize(move |widget| {
+            let Some(window) = widget.window() else {
+                return;
+            };
+
+            let cursor = Cursor::from_name(&window.display(), &name);
+            if cursor.is_none() {
+                warn!("Unknown cursor name: {name}");
+            }
+
+            window.set_cursor(cursor.as_ref());
+        });
+    }
+
+    fn set_accessible_name(&self, name: &str) {
+        if let Some(accessible) = self.accessible() {
+            accessible.set_name(name);
+        }
+    }
+
     fn get_tag<V: 'static>(&self, key: &str) -> Option<&V> {
         unsafe { self.data(key).map(|val| val.as_ref()) }
     }
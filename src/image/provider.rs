@@ -2,6 +2,7 @@ use crate::desktop_file::DesktopFiles;
 use crate::{arc_mut, lock};
 use color_eyre::{Help, Report, Result};
 use gtk::cairo::Surface;
+use gtk::gdk::RGBA;
 use gtk::gdk::ffi::gdk_cairo_surface_create_from_pixbuf;
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::{Cancellable, MemoryInputStream};
@@ -42,7 +43,9 @@ enum ImageLocation {
 #[derive(Debug)]
 struct Cache {
     location_cache: HashMap<(Box<str>, i32), ImageRef>,
-    pixbuf_cache: HashMap<ImageRef, Option<Pixbuf>>,
+    /// Keyed by the image ref, scale factor and symbolic colour,
+    /// as the pixbuf is rendered at the scaled size in that colour.
+    pixbuf_cache: HashMap<(ImageRef, i32, Option<String>), Option<Pixbuf>>,
 }
 
 impl Cache {
@@ -90,18 +93,81 @@ impl Provider {
         size: i32,
         use_fallback: bool,
         image: &Image,
+    ) -> Result<bool> {
+        self.load_into_image_with_color(input, size, use_fallback, None, image)
+            .await
+    }
+
+    /// Attempts to load the provided input into the image,
+    /// loading `fallback` in its place if the input cannot be found or fails to load.
+    ///
+    /// If `color` is provided, symbolic icons from the icon theme are recoloured using it.
+    ///
+    /// Returns `true` if either image was successfully loaded,
+    /// or `false` if neither image could be found.
+    /// May also return an error if loading the fallback failed.
+    pub async fn load_into_image_or(
+        &self,
+        input: &str,
+        fallback: &str,
+        size: i32,
+        color: Option<&RGBA>,
+        image: &Image,
+    ) -> Result<bool> {
+        match self
+            .load_into_image_with_color(input, size, false, color, image)
+            .await
+        {
+            Ok(true) => return Ok(true),
+            Ok(false) => warn!("failed to resolve image: {input}, using fallback: {fallback}"),
+            Err(e) => warn!("failed to load image: {input}, using fallback: {fallback}: {e:?}"),
+        }
+
+        self.load_into_image_with_color(fallback, size, false, color, image)
+            .await
+    }
+
+    /// Like [`Provider::load_into_image_or`], but does not return an error if neither image could be found.
+    ///
+    /// If an image is not resolved, a warning is logged. Errors are also logged.
+    pub async fn load_into_image_or_silent(
+        &self,
+        input: &str,
+        fallback: &str,
+        size: i32,
+        color: Option<&RGBA>,
+        image: &Image,
+    ) {
+        match self
+            .load_into_image_or(input, fallback, size, color, image)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => warn!("failed to resolve image: {fallback}"),
+            Err(e) => warn!("failed to load image: {fallback}: {e:?}"),
+        }
+    }
+
+    async fn load_into_image_with_color(
+        &self,
+        input: &str,
+        size: i32,
+        use_fallback: bool,
+        color: Option<&RGBA>,
+        image: &Image,
     ) -> Result<bool> {
         let image_ref = self.get_ref(input, size).await?;
         debug!("image ref for {input}: {:?}", image_ref);
 
-        let pixbuf = if let Some(pixbuf) = lock!(self.cache).pixbuf_cache.get(&image_ref) {
+        let scale = image.scale_factor();
+        let key = (image_ref, scale, color.map(ToString::to_string));
+
+        let pixbuf = if let Some(pixbuf) = lock!(self.cache).pixbuf_cache.get(&key) {
             pixbuf.clone()
         } else {
-            let pixbuf = Self::get_pixbuf(&image_ref, image.scale_factor(), use_fallback).await?;
+            let pixbuf = Self::get_pixbuf(&key.0, scale, use_fallback, color).await?;
 
-            lock!(self.cache)
-                .pixbuf_cache
-                .insert(image_ref, pixbuf.clone());
+            lock!(self.cache).pixbuf_cache.insert(key, pixbuf.clone());
 
             pixbuf
         };
@@ -230,20 +296,33 @@ impl Provider {
     ///
     /// If `use_fallback` is `true`, a fallback icon will be used
     /// where an image cannot be found.
+    ///
+    /// If `color` is provided, symbolic theme icons are recoloured using it.
     async fn get_pixbuf(
         image_ref: &ImageRef,
         scale: i32,
         use_fallback: bool,
+        color: Option<&RGBA>,
     ) -> Result<Option<Pixbuf>> {
         const FALLBACK_ICON_NAME: &str = "dialog-question-symbolic";
 
         let buf = match &image_ref.location {
-            Some(ImageLocation::Icon(name)) => image_ref.theme.load_icon_for_scale(
-                name,
-                image_ref.size,
-                scale,
-                IconLookupFlags::FORCE_SIZE,
-            ),
+            Some(ImageLocation::Icon(name)) => match color {
+                Some(color) => image_ref
+                    .theme
+                    .lookup_icon_for_scale(name, image_ref.size, scale, IconLookupFlags::FORCE_SIZE)
+                    .map(|info| {
+                        info.load_symbolic(color, None, None, None)
+                            .map(|(pixbuf, _)| pixbuf)
+                    })
+                    .transpose(),
+                None => image_ref.theme.load_icon_for_scale(
+                    name,
+                    image_ref.size,
+                    scale,
+                    IconLookupFlags::FORCE_SIZE,
+                ),
+            },
             Some(ImageLocation::Local(path)) => {
                 let scaled_size = image_ref.size * scale;
                 Pixbuf::from_file_at_scale(path, scaled_size, scaled_size, true).map(Some)
@@ -325,6 +404,9 @@ impl Provider {
         } else {
             IconTheme::default()
         };
+
+        // cached locations refer to the previous theme
+        *lock!(self.cache) = Cache::new();
     }
 }
 
@@ -2,13 +2,40 @@ use super::Ipc;
 use crate::ipc::{Command, Response};
 use color_eyre::Result;
 use color_eyre::{Help, Report};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixStream;
 
 impl Ipc {
     /// Sends a command to the IPC server.
     /// The server response is returned.
     pub async fn send(&self, command: Command, debug: bool) -> Result<Response> {
+        let mut stream = self.connect(&command, debug).await?;
+
+        // the server closes the connection once the response is written,
+        // so read until then to avoid truncating large responses
+        let mut read_buffer = Vec::new();
+        stream.read_to_end(&mut read_buffer).await?;
+
+        let response = serde_json::from_slice(&read_buffer)?;
+        Ok(response)
+    }
+
+    /// Sends a streaming command to the IPC server.
+    /// Each line the server sends is printed to `stdout`,
+    /// until the server closes the connection.
+    pub async fn send_streaming(&self, command: Command, debug: bool) -> Result<()> {
+        let stream = self.connect(&command, debug).await?;
+        let mut lines = BufReader::new(stream).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            println!("{line}");
+        }
+
+        Ok(())
+    }
+
+    /// Connects to the IPC server and writes the command.
+    async fn connect(&self, command: &Command, debug: bool) -> Result<UnixStream> {
         let mut stream = match UnixStream::connect(&self.path).await {
             Ok(stream) => Ok(stream),
             Err(err) => Err(Report::new(err)
@@ -16,18 +43,14 @@ impl Ipc {
                 .suggestion("Is Ironbar running?")),
         }?;
 
-        let write_buffer = serde_json::to_vec(&command)?;
+        let write_buffer = serde_json::to_vec(command)?;
 
         if debug {
-            eprintln!("REQUEST JSON: {}", serde_json::to_string(&command)?);
+            eprintln!("REQUEST JSON: {}", serde_json::to_string(command)?);
         }
 
         stream.write_all(&write_buffer).await?;
 
-        let mut read_buffer = vec![0; 1024];
-        let bytes = stream.read(&mut read_buffer).await?;
-
-        let response = serde_json::from_slice(&read_buffer[..bytes])?;
-        Ok(response)
+        Ok(stream)
     }
 }
@@ -29,6 +29,22 @@ pub enum Command {
 
     /// Interact with a specific bar.
     Bar(BarCommand),
+
+    /// Get the current state of the batteries tracked by `upower` modules.
+    #[cfg(feature = "upower")]
+    Battery(BatteryCommand),
+}
+
+impl Command {
+    /// Whether the server keeps the connection open after this command,
+    /// streaming messages instead of sending a single response.
+    pub fn is_streaming(&self) -> bool {
+        match self {
+            #[cfg(feature = "upower")]
+            Self::Battery(command) => command.watch,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize)]
@@ -55,6 +71,27 @@ pub enum IronvarCommand {
     List { namespace: Option<Box<str>> },
 }
 
+#[cfg(feature = "upower")]
+#[derive(Args, Debug, Serialize, Deserialize)]
+pub struct BatteryCommand {
+    /// Return the full state of every tracked battery as JSON.
+    #[arg(long)]
+    #[serde(default)]
+    pub json: bool,
+
+    /// Format string to print the primary battery with.
+    /// Uses the same tokens as the `upower` module's `format` option.
+    #[arg(long("format"))]
+    pub format: Option<String>,
+
+    /// Keep the connection open,
+    /// and receive a JSON message whenever the battery state changes.
+    /// `json` and `format` are ignored.
+    #[arg(long)]
+    #[serde(default)]
+    pub watch: bool,
+}
+
 #[derive(Args, Debug, Serialize, Deserialize)]
 pub struct BarCommand {
     /// The name of the bar.
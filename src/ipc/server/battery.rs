@@ -0,0 +1,103 @@
+use crate::Ironbar;
+use crate::clients::upower::{DeviceStore, StoreEvent};
+use crate::ipc::{BatteryCommand, Response};
+use crate::modules::upower::{TokenConfig, format_properties, primary_device};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixStream;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, warn};
+
+const DEFAULT_FORMAT: &str = "{percentage}% {state}";
+
+pub fn handle_command(command: &BatteryCommand, ironbar: &Ironbar) -> Response {
+    let store = ironbar.clients.borrow_mut().upower_devices();
+    let devices = store.get_all();
+
+    if command.json {
+        // sort by object path for stable output
+        let devices = devices.into_iter().collect::<BTreeMap<_, _>>();
+
+        return match serde_json::to_string(&devices) {
+            Ok(value) => Response::OkValue { value },
+            Err(err) => Response::error(&err.to_string()),
+        };
+    }
+
+    let Some(properties) = primary_device(&devices, &[]) else {
+        return Response::error("No batteries found");
+    };
+
+    let format = command.format.as_deref().unwrap_or(DEFAULT_FORMAT);
+    let value = format_properties(
+        format,
+        properties,
+        store.ac_online(),
+        store.power_profile().as_deref(),
+        devices.len(),
+        &TokenConfig::default(),
+    );
+
+    Response::OkValue { value }
+}
+
+/// Streams changes to the tracked batteries to the connection,
+/// until the client disconnects.
+///
+/// The current state is sent first as a series of changes,
+/// so that clients can build their state from the messages alone.
+/// Each message is written as a single line of JSON.
+pub async fn watch(mut stream: UnixStream, store: Arc<DeviceStore>) {
+    let mut rx = store.subscribe();
+
+    let mut initial = store
+        .get_all()
+        .into_values()
+        .map(StoreEvent::Device)
+        .collect::<Vec<_>>();
+
+    if let Some(online) = store.ac_online() {
+        initial.push(StoreEvent::AcOnline { online });
+    }
+
+    if let Some(profile) = store.power_profile() {
+        initial.push(StoreEvent::PowerProfile { profile });
+    }
+
+    for event in initial {
+        if write_event(&mut stream, &event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(count)) => {
+                warn!("Battery subscriber fell behind, skipped {count} changes");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        if write_event(&mut stream, &event).await.is_err() {
+            debug!("Battery subscriber disconnected");
+            break;
+        }
+    }
+}
+
+/// Writes the event to the stream as a line of JSON.
+async fn write_event(stream: &mut UnixStream, event: &StoreEvent) -> std::io::Result<()> {
+    let mut line = match serde_json::to_vec(event) {
+        Ok(line) => line,
+        Err(err) => {
+            error!("Failed to serialize battery event: {err:?}");
+            return Ok(());
+        }
+    };
+
+    line.push(b'\n');
+    stream.write_all(&line).await
+}
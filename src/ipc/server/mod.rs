@@ -1,9 +1,13 @@
 mod bar;
+#[cfg(feature = "upower")]
+mod battery;
 mod ironvar;
 
 use std::fs;
 use std::path::Path;
 use std::rc::Rc;
+#[cfg(feature = "upower")]
+use std::sync::Arc;
 
 use color_eyre::{Report, Result};
 use gtk::Application;
@@ -15,6 +19,8 @@ use tracing::{debug, error, info, warn};
 
 use super::Ipc;
 use crate::channels::{AsyncSenderExt, MpscReceiverExt};
+#[cfg(feature = "upower")]
+use crate::clients::upower::DeviceStore;
 use crate::ipc::{Command, Response};
 use crate::style::load_css;
 use crate::{Ironbar, spawn};
@@ -29,6 +35,11 @@ impl Ipc {
 
         let path = self.path.clone();
 
+        // streaming commands are handled off the main thread,
+        // so need their state up front
+        #[cfg(feature = "upower")]
+        let battery_store = ironbar.clients.borrow_mut().upower_devices();
+
         if path.exists() {
             warn!("Socket already exists. Did Ironbar exit abruptly?");
             warn!("Attempting IPC shutdown to allow binding to address");
@@ -52,9 +63,16 @@ impl Ipc {
             loop {
                 match listener.accept().await {
                     Ok((stream, _addr)) => {
-                        if let Err(err) =
-                            Self::handle_connection(stream, &cmd_tx, &mut res_rx).await
-                        {
+                        let result = Self::handle_connection(
+                            stream,
+                            &cmd_tx,
+                            &mut res_rx,
+                            #[cfg(feature = "upower")]
+                            &battery_store,
+                        )
+                        .await;
+
+                        if let Err(err) = result {
                             error!("{err:?}");
                         }
                     }
@@ -75,21 +93,30 @@ impl Ipc {
     /// reads the command message, and sends the response.
     ///
     /// The connection is closed once the response has been written.
+    /// Streaming commands instead keep the connection open in a separate task,
+    /// so that other connections can still be accepted.
     async fn handle_connection(
         mut stream: UnixStream,
         cmd_tx: &Sender<Command>,
         res_rx: &mut Receiver<Response>,
+        #[cfg(feature = "upower")] battery_store: &Arc<DeviceStore>,
     ) -> Result<()> {
-        let (mut stream_read, mut stream_write) = stream.split();
-
         let mut read_buffer = vec![0; 1024];
-        let bytes = stream_read.read(&mut read_buffer).await?;
+        let bytes = stream.read(&mut read_buffer).await?;
 
         // FIXME: Error on invalid command
         let command = serde_json::from_slice::<Command>(&read_buffer[..bytes])?;
 
         debug!("Received command: {command:?}");
 
+        #[cfg(feature = "upower")]
+        if command.is_streaming() {
+            spawn(battery::watch(stream, battery_store.clone()));
+            return Ok(());
+        }
+
+        let (_, mut stream_write) = stream.split();
+
         cmd_tx.send_expect(command).await;
         let res = res_rx
             .recv()
@@ -150,6 +177,8 @@ impl Ipc {
             }
             Command::Var(cmd) => ironvar::handle_command(cmd),
             Command::Bar(cmd) => bar::handle_command(&cmd, ironbar),
+            #[cfg(feature = "upower")]
+            Command::Battery(cmd) => battery::handle_command(&cmd, ironbar),
         }
     }
 
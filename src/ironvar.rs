@@ -64,6 +64,26 @@ impl VariableManager {
                 .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
     }
 
+    /// Sets the value for a variable published by a module, such as `battery.percentage`,
+    /// creating it if it does not exist.
+    ///
+    /// Unlike [`WritableNamespace::set`], the key is not validated,
+    /// allowing a `.` to group variables under the module's name.
+    /// As `set` rejects these keys, they cannot be changed over IPC.
+    ///
+    /// The value is only broadcast if it has changed.
+    pub fn publish(&self, key: &str, value: String) {
+        let mut variables = write_lock!(self.variables);
+
+        if let Some(var) = variables.get_mut(key) {
+            if var.value.as_deref() != Some(value.as_str()) {
+                var.set(Some(value));
+            }
+        } else {
+            variables.insert(key.into(), IronVar::new(Some(value)));
+        }
+    }
+
     pub fn register_namespace<N>(&self, name: &str, namespace: Arc<N>)
     where
         N: Namespace + Sync + Send + 'static,
@@ -74,16 +94,16 @@ impl VariableManager {
 
 impl Namespace for VariableManager {
     fn get(&self, key: &str) -> Option<String> {
-        if key.contains('.') {
-            let (ns, key) = key.split_once('.')?;
-
+        if let Some((ns, ns_key)) = key.split_once('.') {
             let namespaces = read_lock!(self.namespaces);
-            let ns = namespaces.get(ns)?;
 
-            ns.get(key).as_deref().map(ToOwned::to_owned)
-        } else {
-            read_lock!(self.variables).get(key).and_then(IronVar::get)
+            // otherwise fall through to any variable published by a module
+            if let Some(ns) = namespaces.get(ns) {
+                return ns.get(ns_key).as_deref().map(ToOwned::to_owned);
+            }
         }
+
+        read_lock!(self.variables).get(key).and_then(IronVar::get)
     }
 
     fn list(&self) -> Vec<String> {
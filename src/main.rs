@@ -95,6 +95,16 @@ fn run_with_args() {
             let rt = create_runtime();
             rt.block_on(async move {
                 let ipc = ipc::Ipc::new();
+
+                if command.is_streaming() {
+                    if let Err(err) = ipc.send_streaming(command, args.debug).await {
+                        error!("{err:#}");
+                        exit(ExitCode::IpcResponseError as i32)
+                    }
+
+                    return;
+                }
+
                 match ipc.send(command, args.debug).await {
                     Ok(res) => {
                         if args.debug {
@@ -93,6 +93,8 @@ impl Module<Label> for Bindmode {
             }
         });
 
+        self.layout.set_alignment(&label);
+
         Ok(ModuleParts {
             widget: label,
             popup: None,
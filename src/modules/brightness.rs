@@ -0,0 +1,249 @@
+use crate::channels::{AsyncSenderExt, BroadcastReceiverExt};
+use crate::clients::brightness::{self, Brightness, SessionProxy};
+use crate::config::{CommonConfig, LayoutConfig, TruncateMode};
+use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
+use crate::modules::{Module, ModuleInfo, ModuleParts, WidgetContext};
+use crate::{module_impl, spawn};
+use color_eyre::Result;
+use glib::Propagation;
+use gtk::gdk::{EventMask, ScrollDirection};
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout};
+use tracing::{debug, error, warn};
+
+/// How long to wait for further scroll events before writing the new brightness,
+/// so that scrolling quickly only writes the final value.
+const WRITE_DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BrightnessModule {
+    /// The name of the backlight device to show, as found in `/sys/class/backlight`.
+    /// Uses the first device if not set.
+    ///
+    /// **Default**: `null`
+    device: Option<String>,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{percentage}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The number of milliseconds between checking for brightness changes.
+    ///
+    /// **Default**: `500`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The percentage to increase or decrease the brightness by for each scroll step.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_scroll_step")]
+    scroll_step: f64,
+
+    // -- Common --
+    /// See [truncate options](module-level-options#truncate-mode).
+    ///
+    /// **Default**: `null`
+    truncate: Option<TruncateMode>,
+
+    /// See [layout options](module-level-options#layout)
+    #[serde(default, flatten)]
+    layout: LayoutConfig,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{percentage}%")
+}
+
+const fn default_interval() -> u64 {
+    500
+}
+
+const fn default_scroll_step() -> f64 {
+    5.0
+}
+
+impl Module<Button> for BrightnessModule {
+    type SendMessage = Brightness;
+    /// The change in brightness, as a percentage.
+    type ReceiveMessage = f64;
+
+    module_impl!("brightness");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+
+        // reading the brightness only needs sysfs,
+        // so the module still works without logind
+        let session = context
+            .try_client::<SessionProxy>()
+            .inspect_err(|err| {
+                warn!("Failed to connect to logind, brightness is read-only: {err:?}")
+            })
+            .ok();
+
+        let device = self.device.clone();
+        let interval = Duration::from_millis(self.interval);
+
+        spawn(async move {
+            let device = match brightness::find_device(device.as_deref()).await {
+                Ok(Some(device)) => device,
+                Ok(None) => {
+                    error!("No backlight device found");
+                    return;
+                }
+                Err(err) => {
+                    error!("Failed to find backlight device: {err:?}");
+                    return;
+                }
+            };
+
+            debug!("Watching backlight device '{device}'");
+
+            let mut last = None;
+
+            loop {
+                match brightness::read_brightness(&device).await {
+                    Ok(brightness) => tx.send_update_if_changed(brightness, &mut last).await,
+                    Err(err) => error!("Failed to read brightness of '{device}': {err:?}"),
+                }
+
+                select! {
+                    () = sleep(interval) => {}
+                    delta = rx.recv() => match delta {
+                        Some(delta) => {
+                            let Some(brightness) = last else {
+                                continue;
+                            };
+
+                            let (percentage, closed) =
+                                collect_changes(&mut rx, brightness.percentage(), delta).await;
+
+                            if let Some(session) = &session {
+                                set_percentage(session, &device, brightness, percentage).await;
+                            }
+
+                            if closed {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(self.layout.angle(info))
+            .justify(self.layout.justify.into())
+            .build();
+
+        label.add_class("label");
+
+        if let Some(truncate) = self.truncate {
+            label.truncate(truncate);
+        }
+
+        let button = Button::new();
+        button.add(&label);
+        button.add_events(EventMask::SCROLL_MASK | EventMask::SMOOTH_SCROLL_MASK);
+
+        {
+            let tx = context.controller_tx.clone();
+            let step = self.scroll_step;
+
+            button.connect_scroll_event(move |_, event| {
+                let delta = match event.direction() {
+                    ScrollDirection::Up => step,
+                    ScrollDirection::Down => -step,
+                    // touchpads report fractional steps,
+                    // so scale the change to match
+                    ScrollDirection::Smooth => -event.scroll_deltas().unwrap_or_default().1 * step,
+                    _ => return Propagation::Proceed,
+                };
+
+                tx.send_spawn(delta);
+                Propagation::Stop
+            });
+        }
+
+        context
+            .subscribe()
+            .recv_glib(&self.format, move |format, brightness| {
+                label.set_label_escaped(&format_brightness(format, brightness));
+            });
+
+        self.layout.set_alignment(&button);
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
+
+/// Applies `delta` and any further changes received within [`WRITE_DEBOUNCE`] of each other
+/// to the starting percentage, clamping after each change.
+///
+/// Returns the final percentage,
+/// and whether the channel was closed while waiting.
+async fn collect_changes(rx: &mut mpsc::Receiver<f64>, start: f64, delta: f64) -> (f64, bool) {
+    let mut percentage = (start + delta).clamp(0.0, 100.0);
+
+    loop {
+        match timeout(WRITE_DEBOUNCE, rx.recv()).await {
+            Ok(Some(delta)) => percentage = (percentage + delta).clamp(0.0, 100.0),
+            Ok(None) => return (percentage, true),
+            Err(_) => return (percentage, false),
+        }
+    }
+}
+
+/// Sets the brightness to `percentage` percent of the maximum.
+async fn set_percentage(
+    session: &Arc<SessionProxy<'static>>,
+    device: &str,
+    brightness: Brightness,
+    percentage: f64,
+) {
+    let value = brightness.step_towards(percentage);
+    if value == brightness.current {
+        return;
+    }
+
+    if let Err(err) = brightness::set_brightness(session, device, value).await {
+        error!("Failed to set brightness of '{device}': {err:?}");
+    }
+}
+
+/// Replaces the brightness tokens in the format string.
+fn format_brightness(format: &str, brightness: Brightness) -> String {
+    format
+        .replace("{percentage}", &format!("{:.0}", brightness.percentage()))
+        .replace("{brightness}", &brightness.current.to_string())
+        .replace("{max_brightness}", &brightness.max.to_string())
+}
@@ -159,6 +159,8 @@ impl Module<Button> for ClipboardModule {
             tx.send_spawn(ModuleUpdateEvent::TogglePopup(button.popup_id()));
         });
 
+        self.layout.set_alignment(&*button);
+
         let popup = self
             .into_popup(context, info)
             .into_popup_parts(vec![&button]);
@@ -144,6 +144,8 @@ impl Module<Button> for ClockModule {
             label.set_label(&date_string);
         });
 
+        self.layout.set_alignment(&button);
+
         let popup = self
             .into_popup(context, info)
             .into_popup_parts(vec![&button]);
@@ -1,35 +1,10 @@
 use super::{CustomWidget, CustomWidgetContext};
 use crate::build;
-use crate::config::ModuleOrientation;
+use crate::config::{ModuleAlignment, ModuleOrientation};
 use crate::modules::custom::WidgetConfig;
 use gtk::prelude::*;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "lowercase")]
-#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-pub enum ModuleAlignment {
-    /// Align widget to the start (left for horizontal, top for vertical).
-    Start,
-    /// Align widget to the center.
-    Center,
-    /// Align widget to the end (right for horizontal, bottom for vertical).
-    End,
-    /// Stretch widget to fill available space.
-    Fill,
-}
-
-impl From<ModuleAlignment> for gtk::Align {
-    fn from(align: ModuleAlignment) -> Self {
-        match align {
-            ModuleAlignment::Start => gtk::Align::Start,
-            ModuleAlignment::Center => gtk::Align::Center,
-            ModuleAlignment::End => gtk::Align::End,
-            ModuleAlignment::Fill => gtk::Align::Fill,
-        }
-    }
-}
-
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BoxWidget {
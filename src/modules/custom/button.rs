@@ -54,9 +54,13 @@ impl CustomWidget for ButtonWidget {
     fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
         let button = build!(self, Self::Widget);
         context.popup_buttons.borrow_mut().push(button.clone());
+        self.layout.set_alignment(&button);
 
         if let Some(widgets) = self.widgets {
-            let container = gtk::Box::new(self.layout.orientation(context.info), 0);
+            let container = gtk::Box::new(
+                self.layout.orientation(context.info),
+                self.layout.spacing(0),
+            );
 
             for widget in widgets {
                 widget.widget.add_to(&container, &context, widget.common);
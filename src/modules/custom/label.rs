@@ -50,6 +50,7 @@ impl CustomWidget for LabelWidget {
 
         label.set_justify(self.layout.justify.into());
         label.set_use_markup(true);
+        self.layout.set_alignment(&label);
 
         if let Some(truncate) = self.truncate {
             label.truncate(truncate);
@@ -130,7 +130,7 @@ impl Module<gtk::Box> for FocusedModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
-        let container = gtk::Box::new(self.layout.orientation(info), 5);
+        let container = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(5));
 
         let icon = gtk::Image::new();
         if self.show_icon {
@@ -183,6 +183,8 @@ impl Module<gtk::Box> for FocusedModule {
             });
         }
 
+        self.layout.set_alignment(&container);
+
         Ok(ModuleParts {
             widget: container,
             popup: None,
@@ -255,7 +255,7 @@ impl Module<gtk::Box> for KeyboardModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
-        let container = gtk::Box::new(self.layout.orientation(info), 0);
+        let container = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(0));
 
         let image_provider = context.ironbar.image_provider();
 
@@ -342,6 +342,8 @@ impl Module<gtk::Box> for KeyboardModule {
                     layout_button.set_label(text);
                 }
             });
+        self.layout.set_alignment(&container);
+
         Ok(ModuleParts::new(container, None))
     }
 }
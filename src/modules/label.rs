@@ -7,8 +7,14 @@ use crate::modules::{Module, ModuleInfo, ModuleParts, WidgetContext};
 use color_eyre::Result;
 use gtk::Label;
 use serde::Deserialize;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// The minimum time between label updates.
+/// Dynamic strings driven by scripts or ironvars can change many times in quick succession,
+/// so only the latest text in each burst is shown.
+const UPDATE_THROTTLE: Duration = Duration::from_millis(50);
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LabelModule {
@@ -78,9 +84,14 @@ impl Module<Label> for LabelModule {
             label.truncate(truncate);
         }
 
-        context.subscribe().recv_glib(&label, move |label, string| {
-            label.set_label_escaped(&string)
-        });
+        context
+            .subscribe()
+            .throttle(UPDATE_THROTTLE)
+            .recv_glib(&label, move |label, string| {
+                label.set_label_escaped(&string)
+            });
+
+        self.layout.set_alignment(&label);
 
         Ok(ModuleParts {
             widget: label,
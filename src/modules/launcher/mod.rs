@@ -440,7 +440,7 @@ impl Module<gtk::Box> for LauncherModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> crate::Result<ModuleParts<gtk::Box>> {
-        let container = gtk::Box::new(self.layout.orientation(info), 0);
+        let container = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(0));
         let page_size = self.page_size;
 
         let image_provider = context.ironbar.image_provider();
@@ -583,6 +583,8 @@ impl Module<gtk::Box> for LauncherModule {
             );
         }
 
+        self.layout.set_alignment(&container);
+
         let popup = self.into_popup(context, info).into_popup_parts(vec![]); // since item buttons are dynamic, they pass their geometry directly
 
         Ok(ModuleParts {
@@ -12,13 +12,17 @@ use tracing::debug;
 
 use crate::Ironbar;
 use crate::channels::{MpscReceiverExt, SyncSenderExt};
-use crate::clients::{ClientResult, ProvidesClient, ProvidesFallibleClient};
+use crate::clients::{
+    ClientResult, ClientRetry, Clients, FallibleClient, ProvidesClient, ProvidesFallibleClient,
+};
 use crate::config::{BarPosition, CommonConfig, TransitionType};
 use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
 use crate::popup::Popup;
 
 #[cfg(feature = "bindmode")]
 pub mod bindmode;
+#[cfg(feature = "brightness")]
+pub mod brightness;
 #[cfg(feature = "cairo")]
 pub mod cairo;
 #[cfg(feature = "clipboard")]
@@ -91,7 +95,7 @@ pub enum ModuleUpdateEvent<T: Clone> {
     /// Force sets the popup open.
     /// Takes the button ID.
     OpenPopup(usize),
-    #[cfg(feature = "launcher")]
+    #[cfg(any(feature = "launcher", feature = "upower"))]
     OpenPopupAt(WidgetGeometry),
     /// Force sets the popup closed.
     ClosePopup,
@@ -138,6 +142,19 @@ where
         ProvidesFallibleClient::try_provide(self)
     }
 
+    /// Gets a handle for getting client `T` from the controller,
+    /// which retries with exponential backoff until the client can be created.
+    ///
+    /// The longest wait between attempts is set by the top-level `max_retry_delay` option.
+    pub fn retry_client<T>(&self) -> ClientRetry<T>
+    where
+        T: ?Sized + Send + Sync + 'static,
+        Clients: FallibleClient<T>,
+    {
+        let max_delay = self.ironbar.config.borrow().max_retry_delay();
+        ClientRetry::new(self.ironbar.clients.clone(), max_delay)
+    }
+
     /// Subscribes to events sent from this widget.
     pub fn subscribe(&self) -> broadcast::Receiver<TSend> {
         self.update_tx.subscribe()
@@ -411,7 +428,7 @@ impl ModuleFactory for BarModuleFactory {
                 popup.hide();
                 popup.show(id, button_id);
             }
-            #[cfg(feature = "launcher")]
+            #[cfg(any(feature = "launcher", feature = "upower"))]
             ModuleUpdateEvent::OpenPopupAt(geometry) if !disable_popup => {
                 debug!("Opening popup for {} [#{}]", name, id);
 
@@ -488,7 +505,7 @@ impl ModuleFactory for PopupModuleFactory {
                 popup.hide();
                 popup.show(id, button_id);
             }
-            #[cfg(feature = "launcher")]
+            #[cfg(any(feature = "launcher", feature = "upower"))]
             ModuleUpdateEvent::OpenPopupAt(geometry) if !disable_popup => {
                 debug!("Opening popup for {} [#{}]", name, id);
 
@@ -179,7 +179,7 @@ impl Module<Button> for MusicModule {
         info: &ModuleInfo,
     ) -> Result<ModuleParts<Button>> {
         let button = Button::new();
-        let button_contents = gtk::Box::new(self.layout.orientation(info), 5);
+        let button_contents = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(5));
         button_contents.add_class("contents");
 
         button.add(&button_contents);
@@ -254,6 +254,8 @@ impl Module<Button> for MusicModule {
             }
         });
 
+        self.layout.set_alignment(&button);
+
         let popup = self
             .into_popup(context, info)
             .into_popup_parts(vec![&button]);
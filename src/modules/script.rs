@@ -114,6 +114,8 @@ impl Module<Label> for ScriptModule {
             .subscribe()
             .recv_glib(&label, |label, s| label.set_label_escaped(&s));
 
+        self.layout.set_alignment(&label);
+
         Ok(ModuleParts {
             widget: label,
             popup: None,
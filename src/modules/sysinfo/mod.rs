@@ -308,6 +308,8 @@ impl Module<gtk::Box> for SysInfoModule {
             label.set_label_escaped(&data.1);
         });
 
+        self.layout.set_alignment(&container);
+
         Ok(ModuleParts {
             widget: container,
             popup: None,
@@ -0,0 +1,91 @@
+use super::icon_color;
+use crate::image;
+use glib::{ControlFlow, SourceId};
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Number of fill levels to cycle through,
+/// matching the `battery-level-{0..100}-symbolic` themed icons.
+const FRAME_COUNT: u32 = 11;
+
+/// Shown for any fill levels missing from the icon theme.
+const FALLBACK_ICON_NAME: &str = "icon:battery-symbolic";
+
+/// Cycles a themed battery icon through its fill levels
+/// to indicate the battery is charging.
+#[derive(Debug, Clone)]
+pub struct ChargingAnimation {
+    source: Rc<RefCell<Option<SourceId>>>,
+}
+
+impl ChargingAnimation {
+    /// Creates a new animation for the icon.
+    ///
+    /// The animation is stopped automatically when the icon is destroyed.
+    pub fn new(icon: &gtk::Image) -> Self {
+        let animation = Self {
+            source: Rc::default(),
+        };
+
+        {
+            let animation = animation.clone();
+            icon.connect_destroy(move |_| animation.stop());
+        }
+
+        animation
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.source.borrow().is_some()
+    }
+
+    /// Starts cycling the icon, if not already running.
+    ///
+    /// If `recolor` is set, each frame is recoloured using the icon's CSS `color`.
+    pub fn start(&self, icon: &gtk::Image, provider: &image::Provider, size: i32, recolor: bool) {
+        if self.is_running() {
+            return;
+        }
+
+        let frame = Cell::new(0);
+        let icon = icon.clone();
+        let provider = provider.clone();
+
+        let source = glib::timeout_add_local(FRAME_INTERVAL, move || {
+            let level = frame.get() * 10;
+            frame.set((frame.get() + 1) % FRAME_COUNT);
+
+            let icon = icon.clone();
+            let provider = provider.clone();
+            let color = recolor.then(|| icon_color(&icon));
+
+            glib::spawn_future_local(async move {
+                let icon_name = format!("icon:battery-level-{level}-symbolic");
+                provider
+                    .load_into_image_or_silent(
+                        &icon_name,
+                        FALLBACK_ICON_NAME,
+                        size,
+                        color.as_ref(),
+                        &icon,
+                    )
+                    .await;
+            });
+
+            ControlFlow::Continue
+        });
+
+        self.source.replace(Some(source));
+    }
+
+    /// Stops cycling the icon, if running.
+    pub fn stop(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use glib::{ControlFlow, SourceId};
+use gtk::Widget;
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+const BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Flashes a widget by toggling its opacity,
+/// to draw attention to a critically low battery.
+#[derive(Debug, Clone)]
+pub struct Blink {
+    widget: Widget,
+    source: Rc<RefCell<Option<SourceId>>>,
+}
+
+impl Blink {
+    /// Creates a new blink for the widget.
+    ///
+    /// The blink is stopped automatically when the widget is destroyed.
+    pub fn new(widget: &impl IsA<Widget>) -> Self {
+        let blink = Self {
+            widget: widget.clone().upcast(),
+            source: Rc::default(),
+        };
+
+        {
+            let source = blink.source.clone();
+            widget.connect_destroy(move |_| {
+                if let Some(source) = source.take() {
+                    source.remove();
+                }
+            });
+        }
+
+        blink
+    }
+
+    /// Starts flashing the widget, if not already running.
+    pub fn start(&self) {
+        if self.source.borrow().is_some() {
+            return;
+        }
+
+        let widget = self.widget.clone();
+        let is_visible = Cell::new(true);
+
+        let source = glib::timeout_add_local(BLINK_INTERVAL, move || {
+            is_visible.set(!is_visible.get());
+            widget.set_opacity(if is_visible.get() { 1.0 } else { 0.0 });
+
+            ControlFlow::Continue
+        });
+
+        self.source.replace(Some(source));
+    }
+
+    /// Stops flashing the widget, if running,
+    /// and restores its opacity.
+    pub fn stop(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+            self.widget.set_opacity(1.0);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::clients::upower::{BatteryState, UpowerProperties};
+
+/// Fills in any time estimate UPower reports as unavailable,
+/// calculating it from the energy and energy rate instead.
+///
+/// Some hardware reports the energy rate reliably
+/// but never provides its own estimate.
+pub fn estimate_time(properties: &mut UpowerProperties) {
+    if properties.energy_rate <= 0.0 {
+        return;
+    }
+
+    match properties.state {
+        BatteryState::Discharging if properties.time_to_empty == 0 => {
+            properties.time_to_empty = hours_to_seconds(properties.energy / properties.energy_rate);
+        }
+        BatteryState::Charging if properties.time_to_full == 0 => {
+            let remaining = (properties.energy_full - properties.energy).max(0.0);
+            properties.time_to_full = hours_to_seconds(remaining / properties.energy_rate);
+        }
+        _ => {}
+    }
+}
+
+fn hours_to_seconds(hours: f64) -> i64 {
+    (hours * 3600.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(state: BatteryState) -> UpowerProperties {
+        UpowerProperties {
+            object_path: String::new(),
+            native_path: String::from("BAT0"),
+            vendor: String::new(),
+            model: String::new(),
+            device_type: crate::clients::upower::BatteryType::Battery,
+            is_present: true,
+            online: false,
+            percentage: 50.0,
+            icon_name: String::new(),
+            state,
+            time_to_full: 0,
+            time_to_empty: 0,
+            energy: 25.0,
+            energy_full: 50.0,
+            energy_rate: 10.0,
+            charge_threshold_enabled: false,
+            charge_start_threshold: 0,
+            charge_end_threshold: 0,
+            is_bluez: false,
+        }
+    }
+
+    #[test]
+    fn estimates_missing_times() {
+        let mut discharging = properties(BatteryState::Discharging);
+        estimate_time(&mut discharging);
+        assert_eq!(discharging.time_to_empty, 9000);
+
+        let mut charging = properties(BatteryState::Charging);
+        estimate_time(&mut charging);
+        assert_eq!(charging.time_to_full, 9000);
+    }
+
+    #[test]
+    fn keeps_reported_times() {
+        let mut properties = properties(BatteryState::Discharging);
+        properties.time_to_empty = 1200;
+        estimate_time(&mut properties);
+        assert_eq!(properties.time_to_empty, 1200);
+
+        properties.energy_rate = 0.0;
+        properties.time_to_empty = 0;
+        estimate_time(&mut properties);
+        assert_eq!(properties.time_to_empty, 0);
+    }
+}
@@ -0,0 +1,400 @@
+use super::icons::FontIcons;
+use super::time::TimeFormat;
+use crate::clients::upower::{BatteryState, battery_state_to_string};
+use glib::markup_escape_text;
+use serde::Deserialize;
+
+/// Replaces each `{token}` or `{token:spec}` in the template
+/// with the value returned by `lookup` for that token name.
+///
+/// Tokens for which `lookup` returns `None` are left untouched.
+///
+/// The optional spec follows the Rust format syntax for `[[fill]align][width]`,
+/// for example `{m:02}` or `{percentage:>3}`.
+pub fn replace_tokens<F>(template: &str, lookup: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    replace(template, lookup, false)
+}
+
+/// Like [`replace_tokens`], but escapes each value for Pango markup,
+/// so that values containing characters such as `&` or `<`
+/// cannot break any markup in the template.
+pub fn replace_tokens_escaped<F>(template: &str, lookup: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    replace(template, lookup, true)
+}
+
+fn replace<F>(template: &str, mut lookup: F, escape: bool) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let inner = &rest[1..end];
+        let (name, spec) = inner
+            .split_once(':')
+            .map_or((inner, None), |(name, spec)| (name, Some(spec)));
+
+        match lookup(name) {
+            Some(value) => {
+                // pad before escaping so that entities do not count towards the width
+                let value = match spec {
+                    Some(spec) => pad(&value, spec),
+                    None => value,
+                };
+
+                if escape {
+                    output.push_str(&markup_escape_text(&value));
+                } else {
+                    output.push_str(&value);
+                }
+            }
+            None => output.push_str(&rest[..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Surrounds each `{name}` or `{name:spec}` token in the template
+/// with `prefix` and `suffix`, leaving the token itself to be replaced later.
+pub fn wrap_token(template: &str, name: &str, prefix: &str, suffix: &str) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let token = &rest[..=end];
+        let inner = &rest[1..end];
+
+        if inner.split(':').next() == Some(name) {
+            output.push_str(prefix);
+            output.push_str(token);
+            output.push_str(suffix);
+        } else {
+            output.push_str(token);
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Pads the value according to a `[[fill]align][width]` spec.
+///
+/// A leading `0` without an alignment zero-pads the value.
+/// Without an explicit alignment, numeric values are right-aligned
+/// and all other values are left-aligned.
+fn pad(value: &str, spec: &str) -> String {
+    let (fill, align, width) = if let Some(pos) = spec.find(['<', '^', '>']) {
+        let fill = spec[..pos].chars().next().unwrap_or(' ');
+        (fill, spec[pos..].chars().next(), &spec[pos + 1..])
+    } else if let Some(width) = spec.strip_prefix('0') {
+        ('0', Some('>'), width)
+    } else {
+        (' ', None, spec)
+    };
+
+    let width = width.parse::<usize>().unwrap_or_default();
+    let len = value.chars().count();
+
+    if len >= width {
+        return value.to_string();
+    }
+
+    let padding = width - len;
+    let align = align.unwrap_or(if value.parse::<f64>().is_ok() {
+        '>'
+    } else {
+        '<'
+    });
+
+    let (left, right) = match align {
+        '>' => (padding, 0),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (0, padding),
+    };
+
+    let mut output = String::with_capacity(value.len() + padding * fill.len_utf8());
+    output.extend(std::iter::repeat_n(fill, left));
+    output.push_str(value);
+    output.extend(std::iter::repeat_n(fill, right));
+
+    output
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StateFormats {
+    /// The format string to use while charging.
+    /// Falls back to `format` if not set.
+    ///
+    /// **Default**: `null`
+    format_charging: Option<String>,
+
+    /// The format string to use while discharging.
+    /// Falls back to `format` if not set.
+    ///
+    /// **Default**: `null`
+    format_discharging: Option<String>,
+
+    /// The format string to use when fully charged.
+    /// Falls back to `format` if not set.
+    ///
+    /// **Default**: `null`
+    format_full: Option<String>,
+}
+
+impl StateFormats {
+    /// Gets the format string for the battery state,
+    /// falling back to `default` if the state has no override.
+    ///
+    /// The charging format is used for any of the configured charging states.
+    pub fn get<'a>(
+        &'a self,
+        state: BatteryState,
+        tokens: &TokenConfig,
+        default: &'a str,
+    ) -> &'a str {
+        let format = match state {
+            _ if tokens.is_charging(state) => &self.format_charging,
+            BatteryState::Discharging | BatteryState::PendingDischarge => &self.format_discharging,
+            BatteryState::FullyCharged => &self.format_full,
+            _ => &None,
+        };
+
+        format.as_deref().unwrap_or(default)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StateLabels {
+    /// **Default**: `Unknown`
+    unknown: Option<String>,
+    /// **Default**: `Charging`
+    charging: Option<String>,
+    /// **Default**: `Discharging`
+    discharging: Option<String>,
+    /// **Default**: `Empty`
+    empty: Option<String>,
+    /// **Default**: `Fully charged`
+    fully_charged: Option<String>,
+    /// **Default**: `Pending charge`
+    pending_charge: Option<String>,
+    /// **Default**: `Pending discharge`
+    pending_discharge: Option<String>,
+}
+
+impl StateLabels {
+    /// Gets the label for the battery state,
+    /// falling back to the English name if not set.
+    pub fn get(&self, state: BatteryState) -> &str {
+        let label = match state {
+            BatteryState::Unknown => &self.unknown,
+            BatteryState::Charging => &self.charging,
+            BatteryState::Discharging => &self.discharging,
+            BatteryState::Empty => &self.empty,
+            BatteryState::FullyCharged => &self.fully_charged,
+            BatteryState::PendingCharge => &self.pending_charge,
+            BatteryState::PendingDischarge => &self.pending_discharge,
+        };
+
+        label
+            .as_deref()
+            .unwrap_or_else(|| battery_state_to_string(state))
+    }
+}
+
+/// The options used when replacing format tokens.
+#[derive(Debug, Default, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TokenConfig {
+    /// See [time formatting](#time-formatting).
+    #[serde(default, flatten)]
+    pub time: TimeFormat,
+
+    /// See [font icons](#font-icons).
+    #[serde(default, flatten)]
+    pub font_icons: FontIcons,
+
+    /// Labels to use for each battery state in the `{state}` token.
+    /// See [state labels](#state-labels).
+    ///
+    /// **Default**: `{}`
+    #[serde(default)]
+    pub state_labels: StateLabels,
+
+    /// The text to show for the `{ac_online}` token
+    /// while connected to line power.
+    ///
+    /// **Default**: `AC`
+    ac_online_text: Option<String>,
+
+    /// The text to show for the `{ac_online}` token
+    /// while running on battery.
+    ///
+    /// **Default**: `Battery`
+    ac_offline_text: Option<String>,
+
+    /// The text to show for the `{is_present}` token
+    /// while the battery is present.
+    ///
+    /// **Default**: `yes`
+    present_text: Option<String>,
+
+    /// The text to show for the `{is_present}` token
+    /// while the battery is missing, such as when a hot-swappable bay is empty.
+    ///
+    /// **Default**: `no`
+    absent_text: Option<String>,
+
+    /// The battery states which count as charging.
+    ///
+    /// While charging, the popup shows the time until full
+    /// rather than the time until empty, and `format_charging` is used.
+    /// The widget button also has the `is-charging` class,
+    /// the icon is animated if `animate_charging` is set,
+    /// and the battery is shown as full at or above `full_at`.
+    ///
+    /// **Default**: `["charging", "pending_charge"]`
+    charging_states: Option<Vec<BatteryState>>,
+
+    /// The battery states which count as plugged in.
+    /// While plugged in, `{time_remaining}` shows the time until full
+    /// rather than the time until empty.
+    ///
+    /// **Default**: the `charging_states`, plus `fully_charged`
+    full_states: Option<Vec<BatteryState>>,
+
+    /// The number of decimal places to show in the `{percentage}` token.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    precision: usize,
+}
+
+impl TokenConfig {
+    /// Formats the percentage to the configured precision.
+    pub fn percentage(&self, percentage: f64) -> String {
+        if self.precision == 0 {
+            percentage.round().to_string()
+        } else {
+            format!("{percentage:.*}", self.precision)
+        }
+    }
+
+    /// Checks whether the battery state counts as charging.
+    pub fn is_charging(&self, state: BatteryState) -> bool {
+        match &self.charging_states {
+            Some(states) => states.contains(&state),
+            None => matches!(state, BatteryState::Charging | BatteryState::PendingCharge),
+        }
+    }
+
+    /// Checks whether the battery state counts as plugged in.
+    ///
+    /// If `full_states` is not set, this is while charging or once fully charged.
+    pub fn is_plugged_in(&self, state: BatteryState) -> bool {
+        match &self.full_states {
+            Some(states) => states.contains(&state),
+            None => state == BatteryState::FullyCharged || self.is_charging(state),
+        }
+    }
+
+    /// Gets the text for the `{ac_online}` token.
+    ///
+    /// This is empty if there is no line power device.
+    pub fn ac_online(&self, online: Option<bool>) -> &str {
+        match online {
+            Some(true) => self.ac_online_text.as_deref().unwrap_or("AC"),
+            Some(false) => self.ac_offline_text.as_deref().unwrap_or("Battery"),
+            None => "",
+        }
+    }
+
+    /// Gets the text for the `{is_present}` token.
+    pub fn is_present(&self, is_present: bool) -> &str {
+        if is_present {
+            self.present_text.as_deref().unwrap_or("yes")
+        } else {
+            self.absent_text.as_deref().unwrap_or("no")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_tokens() {
+        let template = wrap_token("{percentage:>3}% {state}", "percentage", "<b>", "</b>");
+        assert_eq!(template, "<b>{percentage:>3}</b>% {state}");
+    }
+
+    #[test]
+    fn escapes_tokens() {
+        let lookup = |token: &str| (token == "model").then(|| String::from("Tom & Jerry <3>"));
+
+        assert_eq!(
+            replace_tokens_escaped("<span weight='bold'>{model}</span>", lookup),
+            "<span weight='bold'>Tom &amp; Jerry &lt;3&gt;</span>"
+        );
+        assert_eq!(replace_tokens("{model}", lookup), "Tom & Jerry <3>");
+    }
+
+    #[test]
+    fn plugged_in_states() {
+        let tokens = TokenConfig {
+            charging_states: Some(vec![BatteryState::Charging]),
+            ..TokenConfig::default()
+        };
+
+        assert!(tokens.is_plugged_in(BatteryState::FullyCharged));
+        assert!(!tokens.is_plugged_in(BatteryState::PendingCharge));
+        assert!(!tokens.is_charging(BatteryState::FullyCharged));
+
+        let tokens = TokenConfig {
+            full_states: Some(vec![BatteryState::Charging]),
+            ..TokenConfig::default()
+        };
+
+        assert!(!tokens.is_plugged_in(BatteryState::FullyCharged));
+        assert!(tokens.is_charging(BatteryState::PendingCharge));
+    }
+
+    #[test]
+    fn pads_tokens() {
+        let lookup = |token: &str| (token == "percentage").then(|| String::from("9"));
+
+        assert_eq!(replace_tokens("{percentage:3}%", lookup), "  9%");
+        assert_eq!(replace_tokens("{percentage:<3}%", lookup), "9  %");
+        assert_eq!(replace_tokens("{percentage:03}%", lookup), "009%");
+        assert_eq!(replace_tokens("{other:3}", lookup), "{other:3}");
+    }
+}
@@ -0,0 +1,96 @@
+use super::format::wrap_token;
+use tracing::warn;
+
+/// Colours a percentage along a gradient of evenly spaced colour stops,
+/// from empty to full.
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<[u8; 3]>,
+}
+
+impl Gradient {
+    /// Creates a gradient from a list of `#rrggbb` colours,
+    /// skipping any which are invalid.
+    pub fn new(stops: &[String]) -> Self {
+        let stops = stops
+            .iter()
+            .filter_map(|stop| {
+                let color = parse_color(stop);
+                if color.is_none() {
+                    warn!("Invalid gradient colour '{stop}', expected '#rrggbb'");
+                }
+                color
+            })
+            .collect();
+
+        Self { stops }
+    }
+
+    /// Gets the colour for the percentage as `#rrggbb`,
+    /// or `None` if there are no valid stops.
+    pub fn color_at(&self, percentage: f64) -> Option<String> {
+        let last = self.stops.len().checked_sub(1)?;
+
+        let position = (percentage / 100.0).clamp(0.0, 1.0) * last as f64;
+        let index = (position.floor() as usize).min(last);
+        let next = (index + 1).min(last);
+        let fraction = position - index as f64;
+
+        let [r, g, b] = [0, 1, 2].map(|channel| {
+            let start = f64::from(self.stops[index][channel]);
+            let end = f64::from(self.stops[next][channel]);
+            (end - start).mul_add(fraction, start).round() as u8
+        });
+
+        Some(format!("#{r:02x}{g:02x}{b:02x}"))
+    }
+
+    /// Wraps each `{percentage}` token in the template
+    /// in a Pango span coloured for the percentage.
+    pub fn apply(&self, template: &str, percentage: f64) -> String {
+        match self.color_at(percentage) {
+            Some(color) => wrap_token(
+                template,
+                "percentage",
+                &format!("<span color=\"{color}\">"),
+                "</span>",
+            ),
+            None => template.to_string(),
+        }
+    }
+}
+
+/// Parses a `#rrggbb` colour.
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Gradient {
+        Gradient::new(&[String::from("#ff0000"), String::from("#00ff00")])
+    }
+
+    #[test]
+    fn interpolates_stops() {
+        let gradient = gradient();
+        assert_eq!(gradient.color_at(0.0).as_deref(), Some("#ff0000"));
+        assert_eq!(gradient.color_at(50.0).as_deref(), Some("#808000"));
+        assert_eq!(gradient.color_at(100.0).as_deref(), Some("#00ff00"));
+    }
+
+    #[test]
+    fn skips_invalid_stops() {
+        let gradient = Gradient::new(&[String::from("red")]);
+        assert_eq!(gradient.color_at(50.0), None);
+        assert_eq!(gradient.apply("{percentage}%", 50.0), "{percentage}%");
+    }
+}
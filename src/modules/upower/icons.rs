@@ -0,0 +1,107 @@
+use crate::clients::upower::{BatteryState, UpowerProperties};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IconSet {
+    /// Uses the themed icon provided by UPower.
+    #[default]
+    Theme,
+
+    /// Uses font glyphs in place of the themed icon.
+    /// The glyph is available using the `{icon_text}` token.
+    Font,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FontIcons {
+    /// The glyphs to show while not charging, from empty to full.
+    /// The glyph is picked by dividing the percentage evenly across the list.
+    ///
+    /// **Default**: `["󰂎", "󰁺", "󰁻", "󰁼", "󰁽", "󰁾", "󰁿", "󰂀", "󰂁", "󰂂", "󰁹"]`
+    #[serde(default = "default_font_icons")]
+    font_icons: Vec<String>,
+
+    /// The glyph to show while charging.
+    ///
+    /// **Default**: `󰂄`
+    #[serde(default = "default_font_charging_icon")]
+    font_charging_icon: String,
+
+    /// The glyph to show while waiting to charge.
+    /// Falls back to `font_charging_icon` if not set.
+    ///
+    /// **Default**: `null`
+    font_pending_charge_icon: Option<String>,
+
+    /// The glyph to show while waiting to discharge.
+    /// Falls back to the `font_icons` glyph for the percentage if not set.
+    ///
+    /// **Default**: `null`
+    font_pending_discharge_icon: Option<String>,
+}
+
+impl Default for FontIcons {
+    fn default() -> Self {
+        Self {
+            font_icons: default_font_icons(),
+            font_charging_icon: default_font_charging_icon(),
+            font_pending_charge_icon: None,
+            font_pending_discharge_icon: None,
+        }
+    }
+}
+
+fn default_font_icons() -> Vec<String> {
+    [
+        "\u{f008e}",
+        "\u{f007a}",
+        "\u{f007b}",
+        "\u{f007c}",
+        "\u{f007d}",
+        "\u{f007e}",
+        "\u{f007f}",
+        "\u{f0080}",
+        "\u{f0081}",
+        "\u{f0082}",
+        "\u{f0079}",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_font_charging_icon() -> String {
+    String::from("\u{f0084}")
+}
+
+impl FontIcons {
+    /// Gets the glyph for the device's current percentage and state.
+    pub fn glyph(&self, properties: &UpowerProperties) -> &str {
+        match properties.state {
+            BatteryState::Charging => return &self.font_charging_icon,
+            BatteryState::PendingCharge => {
+                return self
+                    .font_pending_charge_icon
+                    .as_deref()
+                    .unwrap_or(&self.font_charging_icon);
+            }
+            BatteryState::PendingDischarge => {
+                if let Some(icon) = &self.font_pending_discharge_icon {
+                    return icon;
+                }
+            }
+            _ => {}
+        }
+
+        let Some(last) = self.font_icons.len().checked_sub(1) else {
+            return "";
+        };
+
+        let fraction = (properties.percentage / 100.0).clamp(0.0, 1.0);
+        let index = (fraction * last as f64).round() as usize;
+
+        &self.font_icons[index.min(last)]
+    }
+}
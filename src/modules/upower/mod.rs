@@ -0,0 +1,2389 @@
+use color_eyre::{Report, Result};
+use futures_lite::stream::{Boxed, StreamExt};
+use glib::Propagation;
+use gtk::gdk::{Cursor, RGBA, keys};
+use gtk::{Button, prelude::*};
+use gtk::{DirectionType, Label, LevelBar, Orientation};
+use gtk_layer_shell::LayerShell;
+use serde::Deserialize;
+use std::borrow::Borrow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::pending;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::{Instant, sleep, timeout_at};
+use tracing::{debug, error, warn};
+use zbus::fdo::ObjectManagerProxy;
+use zbus::proxy::PropertyStream;
+use zbus::zvariant::OwnedObjectPath;
+
+#[cfg(feature = "ipc")]
+use crate::Ironbar;
+use crate::channels::{AsyncSenderExt, BroadcastReceiverExt};
+use crate::clients::ClientRetry;
+use crate::clients::logind::{ManagerProxy, PrepareForSleepStream};
+use crate::clients::power_profiles::{self, PowerProfilesProxy};
+use crate::clients::upower::{
+    self, BatteryState, Device, DeviceStore, StoreHandle, UPowerProxy, UpowerProperties,
+    battery_state_to_string,
+};
+use crate::config::{CommonConfig, LayoutConfig, ModuleOrientation, TruncateMode};
+use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
+use crate::modules::PopupButton;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext,
+};
+use crate::{module_impl, spawn};
+
+mod animation;
+mod blink;
+mod estimate;
+mod format;
+mod gradient;
+mod icons;
+mod ring;
+mod smoothing;
+mod time;
+
+use self::animation::ChargingAnimation;
+use self::blink::Blink;
+use self::estimate::estimate_time;
+pub use self::format::TokenConfig;
+use self::format::{StateFormats, replace_tokens, replace_tokens_escaped};
+use self::gradient::Gradient;
+use self::icons::IconSet;
+use self::ring::Ring;
+use self::smoothing::EstimateSmoother;
+use self::time::seconds_to_string;
+
+/// How often to re-read Bluetooth batteries from BlueZ.
+/// BlueZ does not signal percentage changes through its object manager,
+/// so these are only picked up periodically.
+const BLUEZ_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Style {
+    /// Shows the battery icon provided by UPower.
+    #[default]
+    Icon,
+
+    /// Shows a ring, filled to match the battery percentage.
+    Ring,
+
+    /// Shows a level bar, filled to match the battery percentage.
+    Bar,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IconPosition {
+    /// Places the indicator before the label.
+    #[default]
+    Start,
+
+    /// Places the indicator after the label.
+    End,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ClickAction {
+    /// Opens the popup.
+    #[default]
+    Popup,
+
+    /// Switches the label between `format` and `format_alt`.
+    ToggleFormat,
+
+    /// Opens the popup, and switches the label between `format` and `format_alt`.
+    Both,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UpowerModule {
+    /// The format string to use for the widget button label.
+    ///
+    /// Available tokens are `{percentage}`, `{state}`, `{time_remaining}`,
+    /// `{seconds_remaining}`, `{time_to_full}`, `{time_to_empty}`, `{icon_text}`,
+    /// `{ac_online}`, `{is_present}`, `{charge_limit}`, `{power_profile}`,
+    /// `{device_count}` and `{model}`.
+    /// Tokens accept a width and alignment, for example `{percentage:>3}`.
+    /// For details, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{percentage}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The format string to use in place of `format` on vertical bars.
+    /// Accepts the same tokens as `format`.
+    /// Falls back to `format` if not set.
+    ///
+    /// **Default**: `null`
+    format_vertical: Option<String>,
+
+    /// See [state formats](#state-formats).
+    #[serde(default, flatten)]
+    state_formats: StateFormats,
+
+    /// An alternate format string for the label,
+    /// which can be switched to by clicking the widget.
+    /// Accepts the same tokens as `format`.
+    ///
+    /// While shown, this replaces `format` and any state formats.
+    /// Requires `click_action` to be `toggle_format` or `both`.
+    ///
+    /// **Default**: `null`
+    format_alt: Option<String>,
+
+    /// Whether to colour the `{percentage}` token in the label
+    /// along `gradient_stops`, based on the percentage.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    gradient: bool,
+
+    /// The colours of the percentage gradient, from empty to full, as `#rrggbb`.
+    /// The stops are spaced evenly, and colours between them are blended.
+    /// Requires `gradient`.
+    ///
+    /// **Default**: `["#e01b24", "#f6d32d", "#33d17a"]`
+    #[serde(default = "default_gradient_stops")]
+    gradient_stops: Vec<String>,
+
+    /// What happens when the widget is clicked.
+    ///
+    /// **Valid options**: `popup`, `toggle_format`, `both`
+    /// <br />
+    /// **Default**: `popup`
+    #[serde(default)]
+    click_action: ClickAction,
+
+    /// Whether to open the popup centred on the pointer,
+    /// rather than on the widget.
+    /// The popup is kept within the edges of the screen.
+    ///
+    /// Useful for wide widgets, or on large screens.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    popup_at_pointer: bool,
+
+    /// The format string to use for the widget button tooltip.
+    /// Accepts the same tokens as `format`, and supports Pango markup.
+    ///
+    /// This takes precedence over the common `tooltip` option.
+    ///
+    /// **Default**: `null`
+    tooltip_format: Option<String>,
+
+    /// The size to render the icon at, in pixels.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_icon_size")]
+    icon_size: i32,
+
+    /// Whether to multiply `icon_size` by the output's scale factor.
+    /// When disabled, `icon_size` is the size in physical pixels,
+    /// so icons appear smaller on HiDPI outputs.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    scale_icon: bool,
+
+    /// The indicator to show next to the label.
+    ///
+    /// **Valid options**: `icon`, `ring`, `bar`
+    /// <br />
+    /// **Default**: `icon`
+    #[serde(default)]
+    style: Style,
+
+    /// Whether to show the indicator chosen by `style`.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    show_icon: bool,
+
+    /// Whether to show the label.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    show_label: bool,
+
+    /// Whether the indicator is placed before or after the label.
+    ///
+    /// **Valid options**: `start`, `end`
+    /// <br />
+    /// **Default**: `start`
+    #[serde(default)]
+    icon_position: IconPosition,
+
+    /// The set of icons to use.
+    ///
+    /// When using `font`, the themed icon is hidden
+    /// and the glyph can be placed in the label using the `{icon_text}` token.
+    ///
+    /// **Valid options**: `theme`, `font`
+    /// <br />
+    /// **Default**: `theme`
+    #[serde(default)]
+    icon_set: IconSet,
+
+    /// Whether to add classes to the icon based on the battery state,
+    /// such as `icon-charging` or `icon-critical`, allowing it to be coloured.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    colored: bool,
+
+    /// The percentage at or below which the battery is considered critical.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_critical_threshold")]
+    critical_threshold: f64,
+
+    /// The percentage at or above which a charging battery is shown as fully charged.
+    ///
+    /// Some batteries stop charging just short of 100%, especially when charge-limited,
+    /// so never report being fully charged.
+    /// At or above this percentage, they are shown with the fully charged state and icon instead.
+    ///
+    /// **Default**: `100`
+    #[serde(default = "default_full_at")]
+    full_at: f64,
+
+    /// Whether to dim the widget contents while fully charged on line power,
+    /// so that a full and idle battery is less distracting.
+    /// The button also has the `dimmed` class while dimmed.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    dim_when_full: bool,
+
+    /// The opacity of the widget contents while dimmed, between `0` and `1`.
+    /// Requires `dim_when_full`.
+    ///
+    /// **Default**: `0.5`
+    #[serde(default = "default_dim_opacity")]
+    dim_opacity: f64,
+
+    /// Whether to cycle the icon through its fill levels while charging.
+    /// Only applies when using themed icons.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    animate_charging: bool,
+
+    /// Whether to flash the indicator while discharging
+    /// at or below `critical_threshold`.
+    /// When not showing an indicator, or when using font icons, the label flashes instead.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    blink_critical: bool,
+
+    /// Whether to recolour symbolic themed icons using the CSS `color` of the icon.
+    /// Combined with `colored`, this allows the icon colour to follow the battery state.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    recolor_icon: bool,
+
+    /// The icon to show if the icon provided by UPower cannot be found,
+    /// such as when the icon theme does not include it.
+    ///
+    /// **Default**: `icon:battery-missing-symbolic`
+    #[serde(default = "default_fallback_icon")]
+    fallback_icon: String,
+
+    /// An image to overlay on the bottom corner of the battery icon while charging,
+    /// such as a lightning bolt.
+    /// This keeps the fill level of the icon visible while charging.
+    ///
+    /// The overlay is shown at half of `icon_size`.
+    /// Only applies when using themed icons.
+    ///
+    /// **Default**: `null`
+    charging_overlay_icon: Option<String>,
+
+    /// The diameter of the ring, in pixels.
+    /// Only applies when `style` is `ring`.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_icon_size")]
+    ring_size: i32,
+
+    /// The thickness of the ring, in pixels.
+    /// Only applies when `style` is `ring`.
+    ///
+    /// **Default**: `3.0`
+    #[serde(default = "default_ring_thickness")]
+    ring_thickness: f64,
+
+    /// The device to track, matched against its `NativePath` or `Model`.
+    /// The first device containing this string is used.
+    ///
+    /// If no device matches, or this is not set,
+    /// the UPower composite display device is used.
+    ///
+    /// **Default**: `null`
+    device: Option<String>,
+
+    /// Whether to track only the UPower composite display device.
+    ///
+    /// When `false`, every battery is tracked.
+    /// The first battery is shown on the bar, and all batteries are listed in the popup.
+    /// Batteries are added and removed as they are plugged in and out.
+    ///
+    /// This has no effect if `device` is set.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    use_display_device: bool,
+
+    /// The order to show devices in when tracking multiple devices,
+    /// as a list of native paths or models.
+    ///
+    /// Listed devices are shown first, in the listed order,
+    /// followed by any other devices sorted by native path.
+    /// The first device is shown on the bar.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    device_order: Vec<String>,
+
+    /// Whether to show the device which is currently charging or discharging on the bar,
+    /// rather than always showing the first device.
+    ///
+    /// This is useful on systems with multiple batteries, which only use one at a time.
+    /// Falls back to the first device if none are charging or discharging.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    prefer_active: bool,
+
+    /// Whether to also list the batteries of Bluetooth devices reported by BlueZ in the popup,
+    /// such as headsets and controllers.
+    ///
+    /// Bluetooth batteries are never shown on the bar.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    bluetooth_batteries: bool,
+
+    /// Whether to hide the module when there is no battery present.
+    ///
+    /// The module reappears automatically if a battery is later connected.
+    /// Bluetooth batteries from `bluetooth_batteries` do not count.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    hide_if_empty: bool,
+
+    /// The orientation to lay out the popup contents.
+    /// When tracking multiple devices, this controls how their rows are stacked.
+    ///
+    /// If not set, this matches the bar orientation.
+    ///
+    /// **Valid options**: `horizontal`, `vertical`
+    /// <br>
+    /// **Default**: `null`
+    popup_orientation: Option<ModuleOrientation>,
+
+    /// The factor to smooth time estimates by, from `0` up to but not including `1`.
+    /// Values outside this range are clamped, with a warning.
+    ///
+    /// Each new estimate is blended with the previous one,
+    /// with higher values giving more weight to the previous estimate.
+    /// This steadies the estimate after plugging in or unplugging,
+    /// at the cost of reacting more slowly to changes.
+    ///
+    /// Set to `0` to disable smoothing.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    smoothing: f64,
+
+    /// Whether to calculate time estimates from the energy rate
+    /// when UPower does not provide them.
+    ///
+    /// This fills in `{time_remaining}` on hardware which reports its energy usage
+    /// but not its own estimates.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    estimate_time: bool,
+
+    /// The time to wait for further changes after a device updates, in milliseconds.
+    /// Changes within this window are combined into a single update,
+    /// reducing redraws on hardware which reports changes in rapid bursts.
+    ///
+    /// Set to `0` to disable.
+    ///
+    /// **Default**: `100`
+    #[serde(default = "default_debounce")]
+    debounce: u64,
+
+    /// See [time formatting](#time-formatting), [font icons](#font-icons)
+    /// and [state labels](#state-labels).
+    #[serde(default, flatten)]
+    tokens: TokenConfig,
+
+    // -- Common --
+    /// See [truncate options](module-level-options#truncate-mode).
+    ///
+    /// **Default**: `null`
+    pub truncate: Option<TruncateMode>,
+
+    /// See [layout options](module-level-options#layout)
+    #[serde(default, flatten)]
+    layout: LayoutConfig,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{percentage}%")
+}
+
+fn default_fallback_icon() -> String {
+    String::from("icon:battery-missing-symbolic")
+}
+
+/// The size in logical pixels to load an icon at.
+/// The image provider renders at this size multiplied by the scale factor,
+/// so dividing it out again leaves `size` as the physical size.
+fn scaled_icon_size(size: i32, scale_icon: bool, image: &gtk::Image) -> i32 {
+    if scale_icon {
+        size
+    } else {
+        (size / image.scale_factor()).max(1)
+    }
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+const fn default_debounce() -> u64 {
+    100
+}
+
+const fn default_critical_threshold() -> f64 {
+    10.0
+}
+
+fn default_gradient_stops() -> Vec<String> {
+    ["#e01b24", "#f6d32d", "#33d17a"].map(String::from).to_vec()
+}
+
+const fn default_dim_opacity() -> f64 {
+    0.5
+}
+
+const fn default_full_at() -> f64 {
+    100.0
+}
+
+const fn default_ring_thickness() -> f64 {
+    3.0
+}
+
+/// Each device's properties are shared behind an `Arc`
+/// so that cloning the map does not deep clone every device.
+type PropertiesMap = HashMap<String, Arc<UpowerProperties>>;
+
+/// The latest properties of the tracked devices,
+/// along with the line power state.
+///
+/// Each widget keeps its own copy,
+/// patching it as each [`UpowerEvent`] is received.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpowerState {
+    devices: PropertiesMap,
+    /// Whether the system is on line power,
+    /// or `None` if there is no line power device.
+    ac_online: Option<bool>,
+    /// The active power profile,
+    /// or `None` if power-profiles-daemon is not available.
+    power_profile: Option<String>,
+    /// The power profiles supported by the system.
+    power_profiles: Vec<String>,
+    /// Whether changing the power profile failed,
+    /// such as if permission was denied.
+    ///
+    /// This is cleared once the active profile next changes.
+    power_profile_failed: bool,
+}
+
+impl UpowerState {
+    fn apply(&mut self, event: UpowerEvent) {
+        match event {
+            UpowerEvent::Reset(state) => *self = Arc::unwrap_or_clone(state),
+            UpowerEvent::Device(key, properties) => {
+                self.devices.insert(key, properties);
+            }
+            UpowerEvent::Remove(key) => {
+                self.devices.remove(&key);
+            }
+            UpowerEvent::AcOnline(online) => self.ac_online = Some(online),
+            // a profile change means the daemon is working again,
+            // such as after it was restarted,
+            // so the profile buttons are re-enabled
+            UpowerEvent::PowerProfile(profile) => {
+                self.power_profile = Some(profile);
+                self.power_profile_failed = false;
+            }
+            UpowerEvent::PowerProfileFailed => self.power_profile_failed = true,
+        }
+    }
+}
+
+/// Messages sent from the widget to the controller.
+#[derive(Debug, Clone)]
+pub enum UpowerCommand {
+    /// Stops watching devices, as the widget has been destroyed.
+    Stop,
+    /// Re-fetches the properties of every device,
+    /// sending a fresh state to the widgets.
+    Refresh,
+    /// Changes the active power profile.
+    SetPowerProfile(String),
+}
+
+/// A change to the upower state.
+///
+/// The full state is only sent on (re)connecting.
+/// After that, only the changed device is sent,
+/// so that the broadcast channel avoids cloning every device on each update.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpowerEvent {
+    /// Replaces the entire state.
+    Reset(Arc<UpowerState>),
+    /// Updates a single device, keyed by object path.
+    Device(String, Arc<UpowerProperties>),
+    /// Removes a single device, keyed by object path.
+    Remove(String),
+    /// Updates whether the system is on line power.
+    AcOnline(bool),
+    /// Updates the active power profile.
+    PowerProfile(String),
+    /// Changing the power profile failed.
+    PowerProfileFailed,
+}
+
+impl Module<Button> for UpowerModule {
+    type SendMessage = UpowerEvent;
+    type ReceiveMessage = UpowerCommand;
+
+    module_impl!("upower");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+
+        // UPower may not be running yet early in the session,
+        // in which case the client keeps retrying until it appears
+        let upower = context.retry_client::<UPowerProxy>();
+
+        // power-profiles-daemon is optional,
+        // and a missing daemon is handled once its properties are read
+        let power_profiles = context.retry_client::<PowerProfilesProxy>();
+
+        // used to reload devices after suspend,
+        // as the change streams can stop delivering signals on some hardware
+        let logind = context
+            .try_client::<ManagerProxy>()
+            .inspect_err(|err| debug!("Failed to connect to logind: {err:?}"))
+            .ok();
+
+        let store = context.client::<DeviceStore>();
+        let options = WatchOptions {
+            device: self.device.clone(),
+            use_display_device: self.use_display_device,
+            bluetooth_batteries: self.bluetooth_batteries,
+            smoothing: self.smoothing,
+            estimate_time: self.estimate_time,
+            debounce: Duration::from_millis(self.debounce),
+        };
+
+        spawn(async move {
+            let mut sleep_signals = match &logind {
+                Some(logind) => logind
+                    .receive_prepare_for_sleep()
+                    .await
+                    .inspect_err(|err| warn!("Failed to subscribe to logind sleep signals: {err}"))
+                    .ok(),
+                None => None,
+            };
+
+            // dropping the watch future cancels all of its listeners,
+            // so refreshing or resuming restarts it to reload every device
+            'watch: loop {
+                let watch = watch_upower(&upower, &power_profiles, &options, &store, &tx);
+                tokio::pin!(watch);
+
+                loop {
+                    select! {
+                        command = rx.recv() => match command {
+                            Some(UpowerCommand::Refresh) => {
+                                debug!("Refreshing upower devices");
+                                continue 'watch;
+                            }
+                            Some(UpowerCommand::SetPowerProfile(profile)) => {
+                                spawn(set_power_profile(
+                                    power_profiles.clone(),
+                                    profile,
+                                    tx.clone(),
+                                ));
+                            }
+                            Some(UpowerCommand::Stop) | None => {
+                                debug!("Stopping upower controller");
+                                break 'watch;
+                            }
+                        },
+                        () = wait_for_resume(&mut sleep_signals) => {
+                            debug!("Resumed from suspend, reloading upower devices");
+                            continue 'watch;
+                        }
+                        // a finished future cannot be polled again,
+                        // and it only finishes once the clients are gone
+                        () = &mut watch => {
+                            debug!("Upower client is no longer available, stopping controller");
+                            break 'watch;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let icon = gtk::Image::new();
+        icon.add_class("icon");
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(self.layout.angle(info))
+            .justify(self.layout.justify.into())
+            .build();
+
+        label.add_class("label");
+
+        if let Some(truncate) = self.truncate {
+            label.truncate(truncate);
+        }
+
+        let container = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(5));
+        container.add_class("contents");
+
+        let button = Button::new();
+        button.add_class("button");
+
+        let ring = Ring::new(self.ring_size, self.ring_thickness);
+        let level_bar = create_level_bar(self.layout.orientation(info));
+
+        let charging_overlay = gtk::Image::new();
+        charging_overlay.add_class("charging-overlay");
+        charging_overlay.set_halign(gtk::Align::End);
+        charging_overlay.set_valign(gtk::Align::End);
+        // only shown while charging
+        charging_overlay.set_no_show_all(true);
+
+        if self.show_icon {
+            match self.style {
+                Style::Icon if self.icon_set == IconSet::Font => {}
+                Style::Icon if self.charging_overlay_icon.is_some() => {
+                    let overlay = gtk::Overlay::new();
+                    overlay.add(&icon);
+                    overlay.add_overlay(&charging_overlay);
+                    container.add(&overlay);
+                }
+                Style::Icon => container.add(&icon),
+                Style::Ring => container.add(ring.widget()),
+                Style::Bar => container.add(&level_bar),
+            }
+        }
+
+        if self.show_label {
+            container.add(&label);
+
+            if self.icon_position == IconPosition::End {
+                container.reorder_child(&label, 0);
+            }
+        }
+
+        button.add(&container);
+
+        // show a hand cursor over the button,
+        // unless one is already set using the common `cursor` option
+        button.connect_realize(|button| {
+            let inherited = button.window().and_then(|window| window.cursor());
+            if let (None, Some(window)) = (inherited, button.event_window()) {
+                let cursor = Cursor::from_name(&window.display(), "pointer");
+                window.set_cursor(cursor.as_ref());
+            }
+        });
+
+        let show_alt = Rc::new(Cell::new(false));
+        // the last formatted label for each format,
+        // so that toggling can update the label straight away
+        let label_texts = Rc::new(RefCell::new((String::new(), None::<String>)));
+
+        // the pointer position of the last press, relative to the button
+        let pointer = Rc::new(Cell::new(None::<(f64, f64)>));
+        // tracked so that clicking toggles the popup when opening it at the pointer
+        let popup_visible = Rc::new(Cell::new(false));
+
+        if self.popup_at_pointer {
+            let pointer = pointer.clone();
+            button.connect_button_press_event(move |_, event| {
+                pointer.set(Some(event.position()));
+                Propagation::Proceed
+            });
+        }
+
+        {
+            let tx = context.tx.clone();
+            let click_action = self.click_action;
+            let label = label.clone();
+            let show_alt = show_alt.clone();
+            let label_texts = label_texts.clone();
+            let orientation = info.bar_position.orientation();
+            let pointer = pointer.clone();
+            let popup_visible = popup_visible.clone();
+
+            button.connect_clicked(move |button| {
+                if click_action != ClickAction::ToggleFormat {
+                    let event = match pointer.take() {
+                        Some(_) if popup_visible.get() => ModuleUpdateEvent::ClosePopup,
+                        Some((x, y)) => {
+                            let mut geometry = button.geometry(orientation);
+                            let offset = if orientation == Orientation::Horizontal {
+                                x
+                            } else {
+                                y
+                            };
+
+                            geometry.position += offset as i32;
+                            geometry.size = 0;
+
+                            ModuleUpdateEvent::OpenPopupAt(geometry)
+                        }
+                        None => ModuleUpdateEvent::TogglePopup(button.popup_id()),
+                    };
+
+                    tx.send_spawn(event);
+                }
+
+                if click_action != ClickAction::Popup
+                    && let (text, Some(alt_text)) = &*RefCell::borrow(&label_texts)
+                {
+                    show_alt.set(!show_alt.get());
+                    label.set_label(if show_alt.get() { alt_text } else { text });
+                }
+            });
+        }
+
+        // stop listening once the widget is gone, such as when the bar is reloaded,
+        // so that the controller does not keep the widgets alive
+        let controller_tx = context.controller_tx.clone();
+        button.connect_destroy(move |_| controller_tx.send_spawn(UpowerCommand::Stop));
+
+        if self.hide_if_empty {
+            // start hidden until a battery is reported.
+            // `show_all` on the module container would otherwise show the button,
+            // so its contents are shown here instead.
+            button.set_no_show_all(true);
+            container.show_all();
+
+            let mut state = UpowerState::default();
+
+            context
+                .subscribe()
+                .recv_glib(&button, move |button, event| {
+                    state.apply(event);
+
+                    // BlueZ batteries are only shown in the popup, so do not count them
+                    let has_battery = state
+                        .devices
+                        .values()
+                        .any(|properties| properties.is_present && !properties.is_bluez);
+
+                    button.set_visible(has_battery);
+                });
+        }
+
+        let format = match &self.format_vertical {
+            Some(format) if info.bar_position.orientation() == Orientation::Vertical => {
+                format.clone()
+            }
+            _ => self.format.clone(),
+        };
+        let format_alt = self.format_alt.clone();
+        let state_formats = self.state_formats.clone();
+        let tokens = self.tokens.clone();
+        let tooltip_format = self.tooltip_format.clone();
+
+        let animation = ChargingAnimation::new(&icon);
+
+        let blink = match self.style {
+            _ if !self.show_icon => Blink::new(&label),
+            Style::Icon if self.icon_set == IconSet::Font => Blink::new(&label),
+            Style::Icon => Blink::new(&icon),
+            Style::Ring => Blink::new(ring.widget()),
+            Style::Bar => Blink::new(&level_bar),
+        };
+        let fallback_icon = self.fallback_icon.clone();
+        let uses_theme_icon =
+            self.show_icon && self.style == Style::Icon && self.icon_set == IconSet::Theme;
+
+        let rx = context.subscribe();
+        let provider = context.ironbar.image_provider();
+
+        if uses_theme_icon && let Some(overlay_icon) = self.charging_overlay_icon.clone() {
+            let provider = provider.clone();
+            let charging_overlay = charging_overlay.clone();
+            let size = scaled_icon_size(self.icon_size, self.scale_icon, &charging_overlay) / 2;
+
+            glib::spawn_future_local(async move {
+                provider
+                    .load_into_image_silent(&overlay_icon, size, false, &charging_overlay)
+                    .await;
+            });
+        }
+
+        let device_order = self.device_order.clone();
+        let contents = container.clone();
+        let gradient = self.gradient.then(|| Gradient::new(&self.gradient_stops));
+        let mut state = UpowerState::default();
+        let prefer_active = self.prefer_active;
+        let mut primary_class = None::<String>;
+        let mut profile_class = None::<String>;
+        let mut state_class = None::<&str>;
+        // the last loaded icon name and colour, to avoid reloading an unchanged icon
+        let mut last_icon = None::<(String, Option<String>)>;
+        rx.recv_glib_async(&button, move |button, event| {
+            // only the primary device is shown on the widget,
+            // so changes to any other device can be skipped
+            let previous = bar_device(&state.devices, &device_order, prefer_active)
+                .map(|properties| properties.object_path.clone());
+
+            let key = match &event {
+                UpowerEvent::Device(key, _) | UpowerEvent::Remove(key) => Some(key.clone()),
+                UpowerEvent::Reset(_)
+                | UpowerEvent::AcOnline(_)
+                | UpowerEvent::PowerProfile(_)
+                | UpowerEvent::PowerProfileFailed => None,
+            };
+
+            state.apply(event);
+
+            let class = state
+                .power_profile
+                .as_ref()
+                .map(|profile| format!("power-profile-{profile}"));
+
+            if class != profile_class {
+                if let Some(old_class) = &profile_class {
+                    button.remove_class(old_class);
+                }
+
+                if let Some(class) = &class {
+                    button.add_class(class);
+                }
+
+                profile_class = class;
+            }
+
+            let primary = bar_device(&state.devices, &device_order, prefer_active).cloned();
+
+            let is_relevant = key.is_none_or(|key| {
+                previous.as_ref() == Some(&key)
+                    || primary
+                        .as_ref()
+                        .is_some_and(|properties| properties.object_path == key)
+            });
+
+            let properties = primary
+                .filter(|_| is_relevant)
+                .map(|properties| treat_as_full(&properties, self.full_at, &tokens));
+
+            if is_relevant {
+                let is_charging = properties.as_ref().is_some_and(|properties| {
+                    tokens.is_charging(properties.state)
+                });
+
+                if self.animate_charging && uses_theme_icon && is_charging {
+                    animation.start(
+                        &icon,
+                        &provider,
+                        scaled_icon_size(self.icon_size, self.scale_icon, &icon),
+                        self.recolor_icon,
+                    );
+                } else {
+                    animation.stop();
+                }
+
+                if is_charging {
+                    button.add_class("is-charging");
+                } else {
+                    button.remove_class("is-charging");
+                }
+
+                charging_overlay.set_visible(is_charging);
+
+                let is_critical = properties.as_ref().is_some_and(|properties| {
+                    properties.state == BatteryState::Discharging
+                        && properties.percentage <= self.critical_threshold
+                });
+
+                if self.blink_critical && is_critical {
+                    blink.start();
+                } else {
+                    blink.stop();
+                }
+
+                let class = properties
+                    .as_ref()
+                    .and_then(|properties| button_state_class(properties.state));
+
+                if class != state_class {
+                    if let Some(old_class) = state_class {
+                        button.remove_class(old_class);
+                    }
+
+                    if let Some(class) = class {
+                        button.add_class(class);
+                    }
+
+                    state_class = class;
+                }
+            }
+
+            let is_animating = animation.is_running();
+
+            if let Some(properties) = &properties {
+                let format = state_formats.get(properties.state, &tokens, &format);
+                let power_profile = state.power_profile.as_deref();
+
+                // the gradient adds markup, so must be applied before formatting
+                let colored = |format: &str| match &gradient {
+                    Some(gradient) => gradient.apply(format, properties.percentage),
+                    None => format.to_string(),
+                };
+
+                let text = format_label(
+                    &colored(format),
+                    properties,
+                    state.ac_online,
+                    power_profile,
+                    state.devices.len(),
+                    &tokens,
+                );
+                let alt_text = format_alt.as_ref().map(|format| {
+                    format_label(
+                        &colored(format),
+                        properties,
+                        state.ac_online,
+                        power_profile,
+                        state.devices.len(),
+                        &tokens,
+                    )
+                });
+
+                match &alt_text {
+                    Some(alt_text) if show_alt.get() => label.set_label(alt_text),
+                    _ => label.set_label(&text),
+                }
+
+                label_texts.replace((text, alt_text));
+                button.set_accessible_name(&accessible_text(properties, &tokens));
+
+                #[cfg(feature = "ipc")]
+                publish_variables(properties, &state, &tokens);
+
+                if let Some(tooltip_format) = &tooltip_format {
+                    let tooltip = format_tokens(
+                        tooltip_format,
+                        properties,
+                        state.ac_online,
+                        power_profile,
+                        state.devices.len(),
+                        &tokens,
+                        true,
+                    );
+                    button.set_tooltip_markup(Some(&tooltip));
+                }
+
+                if self.colored {
+                    set_icon_classes(&icon, properties, self.critical_threshold);
+                }
+
+                let class = device_class(properties);
+                if primary_class.as_ref() != Some(&class) {
+                    if let Some(old_class) = primary_class.replace(class.clone()) {
+                        button.remove_class(&old_class);
+                    }
+
+                    button.add_class(&class);
+                }
+
+                if properties.charge_limit().is_some() {
+                    button.add_class("charge-limited");
+                } else {
+                    button.remove_class("charge-limited");
+                }
+
+                let is_dimmed = self.dim_when_full
+                    && properties.state == BatteryState::FullyCharged
+                    && state.ac_online == Some(true);
+
+                if is_dimmed {
+                    contents.set_opacity(self.dim_opacity.clamp(0.0, 1.0));
+                    button.add_class("dimmed");
+                } else {
+                    contents.set_opacity(1.0);
+                    button.remove_class("dimmed");
+                }
+
+                match self.style {
+                    Style::Icon => {}
+                    Style::Ring => ring.update(properties),
+                    Style::Bar => level_bar.set_value(properties.percentage),
+                }
+            }
+
+            let provider = provider.clone();
+            let icon = icon.clone();
+            let color = self.recolor_icon.then(|| icon_color(&icon));
+            let fallback_icon = fallback_icon.clone();
+
+            // the animation replaces the icon, so it must be reloaded once stopped
+            if is_animating {
+                last_icon = None;
+            }
+
+            let mut icon_name = None;
+            if uses_theme_icon
+                && !is_animating
+                && let Some(properties) = &properties
+            {
+                let name = format!("icon:{}", properties.icon_name);
+                let key = (name.clone(), color.map(|color| color.to_string()));
+
+                if last_icon.as_ref() != Some(&key) {
+                    icon_name = Some(name);
+                    last_icon = Some(key);
+                }
+            }
+
+            async move {
+                if let Some(icon_name) = icon_name {
+                    let result = provider
+                        .load_into_image_or(
+                            &icon_name,
+                            &fallback_icon,
+                            scaled_icon_size(self.icon_size, self.scale_icon, &icon),
+                            color.as_ref(),
+                            &icon,
+                        )
+                        .await;
+
+                    match result {
+                        Ok(true) => {}
+                        Ok(false) => error!(
+                            "Failed to find upower icon '{icon_name}' or fallback icon '{fallback_icon}'"
+                        ),
+                        Err(err) => {
+                            error!("Failed to load upower fallback icon '{fallback_icon}': {err:?}");
+                        }
+                    }
+                }
+            }
+        });
+
+        self.layout.set_alignment(&button);
+
+        let popup = self
+            .into_popup(context, info)
+            .into_popup_parts(vec![&button]);
+
+        if let Some(popup) = &popup {
+            let visible = popup_visible.clone();
+            popup.container.connect_map(move |_| visible.set(true));
+            popup
+                .container
+                .connect_unmap(move |_| popup_visible.set(false));
+        }
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let orientation = self
+            .popup_orientation
+            .map_or(info.bar_position.orientation(), ModuleOrientation::into);
+
+        let container = gtk::Box::builder().orientation(orientation).build();
+
+        let devices = gtk::Box::builder().orientation(orientation).build();
+        container.add(&devices);
+
+        let profiles = gtk::Box::new(Orientation::Horizontal, 0);
+        profiles.add_class("upower-profiles");
+        container.add(&profiles);
+
+        let refresh_button = Button::with_label("Refresh");
+        refresh_button.add_class("upower-refresh");
+        container.add(&refresh_button);
+
+        let controller_tx = context.controller_tx.clone();
+        refresh_button.connect_clicked(move |_| controller_tx.send_spawn(UpowerCommand::Refresh));
+
+        // the popup window is shared by every module on the bar,
+        // so keyboard input is only taken while this popup is shown
+        container.connect_map(|container| {
+            set_popup_keyboard_interactivity(container, true);
+            container.child_focus(DirectionType::TabForward);
+        });
+
+        container.connect_unmap(|container| {
+            set_popup_keyboard_interactivity(container, false);
+        });
+
+        {
+            let tx = context.tx.clone();
+            container.connect_key_press_event(move |_, event| {
+                if event.keyval() == keys::constants::Escape {
+                    tx.send_spawn(ModuleUpdateEvent::ClosePopup);
+                    Propagation::Stop
+                } else {
+                    Propagation::Proceed
+                }
+            });
+        }
+
+        let controller_tx = context.controller_tx.clone();
+
+        let device_order = self.device_order.clone();
+        let provider = context.ironbar.image_provider();
+        let icon_size = self.icon_size;
+        let scale_icon = self.scale_icon;
+        let full_at = self.full_at;
+        let mut state = UpowerState::default();
+        let mut labels = HashMap::<String, Label>::new();
+
+        context.subscribe().recv_glib(
+            (&devices, &self.tokens),
+            move |(container, tokens), event| {
+                // existing devices can be updated in place,
+                // but anything else requires rebuilding the labels
+                let changed = match &event {
+                    UpowerEvent::Device(key, _) if labels.contains_key(key) => Some(key.clone()),
+                    _ => None,
+                };
+
+                let is_power_change = matches!(
+                    event,
+                    UpowerEvent::AcOnline(_)
+                        | UpowerEvent::PowerProfile(_)
+                        | UpowerEvent::PowerProfileFailed
+                );
+                let is_profile_change = matches!(
+                    event,
+                    UpowerEvent::Reset(_)
+                        | UpowerEvent::PowerProfile(_)
+                        | UpowerEvent::PowerProfileFailed
+                );
+                state.apply(event);
+
+                if is_profile_change {
+                    update_profile_buttons(&profiles, &state, &controller_tx);
+                }
+
+                if is_power_change {
+                    return;
+                }
+
+                // names are only useful to tell multiple devices apart
+                let show_names = state.devices.len() > 1;
+
+                if let Some(key) = changed {
+                    if let (Some(label), Some(properties)) =
+                        (labels.get(&key), state.devices.get(&key))
+                    {
+                        let properties = treat_as_full(properties, full_at, tokens);
+                        label.set_label_escaped(&popup_text(&properties, tokens, show_names));
+                        label.set_accessible_name(&accessible_text(&properties, tokens));
+                    }
+
+                    return;
+                }
+
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                labels.clear();
+
+                for properties in sorted_devices(&state.devices, &device_order) {
+                    let properties = &treat_as_full(properties, full_at, tokens);
+
+                    if show_names {
+                        let header = Label::builder().use_markup(true).build();
+                        header.add_class("upower-header");
+                        header.add_class(&device_class(properties));
+                        header.set_markup(&format!(
+                            "<b>{}</b>",
+                            glib::markup_escape_text(&properties.title())
+                        ));
+                        container.add(&header);
+                    }
+
+                    // UPower devices are identified by their header,
+                    // but Bluetooth devices also show their device icon
+                    if properties.is_bluez {
+                        let icon = gtk::Image::new();
+                        icon.add_class("upower-device-icon");
+                        icon.add_class(&device_class(properties));
+                        container.add(&icon);
+
+                        let provider = provider.clone();
+                        let icon_name = format!("icon:{}", properties.icon_name);
+                        let icon_size = scaled_icon_size(icon_size, scale_icon, &icon);
+                        glib::spawn_future_local(async move {
+                            provider
+                                .load_into_image_or_silent(
+                                    &icon_name,
+                                    "icon:bluetooth-symbolic",
+                                    icon_size,
+                                    None,
+                                    &icon,
+                                )
+                                .await;
+                        });
+                    }
+
+                    // focusable so that screen readers can step through each device
+                    let label = Label::builder().use_markup(true).can_focus(true).build();
+                    label.add_class("upower-details");
+                    label.add_class(&device_class(properties));
+                    label.set_label_escaped(&popup_text(properties, tokens, show_names));
+                    label.set_accessible_name(&accessible_text(properties, tokens));
+                    container.add(&label);
+
+                    labels.insert(properties.object_path.clone(), label);
+                }
+
+                container.show_all();
+            },
+        );
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+/// Rebuilds the buttons for each power profile,
+/// highlighting the active one.
+///
+/// No buttons are shown if power-profiles-daemon is not available,
+/// and disabled if changing the profile failed.
+fn update_profile_buttons(
+    container: &gtk::Box,
+    state: &UpowerState,
+    controller_tx: &mpsc::Sender<UpowerCommand>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    for profile in &state.power_profiles {
+        let button = Button::with_label(profile);
+        button.add_class("upower-profile");
+        button.add_class(&format!("power-profile-{profile}"));
+        button.set_sensitive(!state.power_profile_failed);
+
+        if state.power_profile.as_ref() == Some(profile) {
+            button.add_class("active");
+        }
+
+        let controller_tx = controller_tx.clone();
+        let profile = profile.clone();
+        button.connect_clicked(move |_| {
+            controller_tx.send_spawn(UpowerCommand::SetPowerProfile(profile.clone()));
+        });
+
+        container.add(&button);
+    }
+
+    container.show_all();
+}
+
+/// Waits for the system to resume from suspend.
+///
+/// Never completes if logind is not available.
+async fn wait_for_resume(signals: &mut Option<PrepareForSleepStream>) {
+    if let Some(signals) = signals {
+        while let Some(signal) = signals.next().await {
+            match signal.args() {
+                Ok(args) if !args.start => return,
+                Ok(_) => {}
+                Err(err) => warn!("Received invalid logind signal arguments: {err}"),
+            }
+        }
+    }
+
+    pending().await
+}
+
+/// Watches the tracked devices,
+/// reconnecting with a backoff whenever the connection is lost.
+///
+/// The proxies are recreated through the shared clients on reconnecting,
+/// which also replaces the system bus connection if the bus itself restarted.
+///
+/// Returns only if the clients can no longer be requested,
+/// which happens once the main thread has stopped.
+async fn watch_upower(
+    upower: &ClientRetry<UPowerProxy<'static>>,
+    power_profiles: &ClientRetry<PowerProfilesProxy<'static>>,
+    options: &WatchOptions,
+    store: &Arc<DeviceStore>,
+    tx: &mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    let mut backoff = upower.backoff();
+    let mut is_reconnect = false;
+
+    loop {
+        let proxy = if is_reconnect {
+            upower.reconnect().await
+        } else {
+            upower.get().await
+        };
+
+        let Some(proxy) = proxy else {
+            return;
+        };
+
+        // the old proxy may be on a connection which has been lost
+        let power_profiles = if is_reconnect {
+            power_profiles.try_reconnect().await
+        } else {
+            power_profiles.try_get().await
+        };
+
+        match watch_devices(&proxy, power_profiles.as_ref(), options, store, tx).await {
+            Ok(()) => {
+                warn!("Lost connection to upower, reconnecting");
+                backoff.reset();
+            }
+            Err(err) => warn!("Failed to connect to upower: {err:?}"),
+        }
+
+        sleep(backoff.next_delay()).await;
+        is_reconnect = true;
+    }
+}
+
+/// Options for which devices to track,
+/// and how to process their updates.
+#[derive(Debug, Clone)]
+struct WatchOptions {
+    device: Option<String>,
+    use_display_device: bool,
+    bluetooth_batteries: bool,
+    smoothing: f64,
+    estimate_time: bool,
+    debounce: Duration,
+}
+
+/// Creates proxies for the tracked devices and listens for changes on each,
+/// sending the initial state once all devices are loaded.
+///
+/// The change listeners are only spawned after the initial state is sent,
+/// so the first update always contains every device.
+///
+/// The line power device is also watched, if one exists,
+/// to track whether the system is on AC power.
+///
+/// When enumerating all devices, batteries are added and removed as they are plugged in,
+/// cancelling the listeners for any removed devices.
+///
+/// Returns once every change stream has closed,
+/// which happens if the bus connection or UPower goes away.
+async fn watch_devices(
+    upower: &UPowerProxy<'static>,
+    power_profiles: Option<&Arc<PowerProfilesProxy<'static>>>,
+    options: &WatchOptions,
+    store: &Arc<DeviceStore>,
+    tx: &mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) -> Result<()> {
+    // the devices are removed from the store once this returns or is cancelled,
+    // including any which were removed from upower while disconnected
+    let store = &Arc::new(store.handle());
+
+    // batteries are only added and removed when enumerating all devices
+    let is_enumerated = options.device.is_none() && !options.use_display_device;
+
+    // subscribe before enumerating so that no devices are missed
+    let hotplug = if is_enumerated {
+        Some((
+            upower.receive_device_added().await?,
+            upower.receive_device_removed().await?,
+        ))
+    } else {
+        None
+    };
+
+    let devices = if let Some(device) = &options.device {
+        let path = if let Some(path) = upower::find_device_path(upower, device).await? {
+            path
+        } else {
+            warn!("No device matching '{device}' found, using display device");
+            upower::display_device_path(upower).await?
+        };
+
+        vec![upower::create_device(upower, path).await?]
+    } else {
+        upower::create_devices(upower, options.use_display_device).await?
+    };
+
+    // fetch the initial properties of every device concurrently
+    let initial = devices
+        .into_iter()
+        .map(|device| {
+            let path = device.path().clone();
+            let handle = spawn(load_device(
+                device,
+                options.smoothing,
+                options.estimate_time,
+            ));
+
+            (path, handle)
+        })
+        .collect::<Vec<_>>();
+
+    let mut state = UpowerState::default();
+    let mut loaded = vec![];
+
+    // a device failing to load should not stop the others from being watched
+    for (path, handle) in initial {
+        let device = match handle.await? {
+            Ok(device) => device,
+            Err(err) => {
+                warn!("Failed to load upower device {path}: {err:?}");
+                continue;
+            }
+        };
+
+        if let Some(device) = device {
+            let properties = device.properties.clone();
+
+            store.insert(properties.clone());
+            state
+                .devices
+                .insert(properties.object_path.clone(), Arc::new(properties));
+
+            loaded.push(device);
+        }
+    }
+
+    let mut line_power = None;
+    if let Some(device) = upower::find_line_power(upower).await? {
+        let mut stream = device.receive_properties().await?;
+
+        if let Some(properties) = stream.next().await {
+            store.set_ac_online(properties.online);
+            state.ac_online = Some(properties.online);
+            line_power = Some((stream, properties.online));
+        }
+    }
+
+    let mut power_profile = None;
+    if let Some(power_profiles) = power_profiles {
+        // subscribe before reading so that no changes are missed
+        let stream = power_profiles.receive_active_profile_changed().await;
+
+        match power_profiles.active_profile().await {
+            Ok(profile) => {
+                store.set_power_profile(profile.clone());
+                state.power_profile = Some(profile.clone());
+                power_profile = Some((stream, profile));
+
+                match power_profiles::profile_names(power_profiles).await {
+                    Ok(names) => state.power_profiles = names,
+                    Err(err) => warn!("Failed to get power profiles: {err}"),
+                }
+            }
+            Err(err) => debug!("power-profiles-daemon is not available: {err}"),
+        }
+    }
+
+    tx.send_update(UpowerEvent::Reset(Arc::new(state))).await;
+
+    // the listeners are aborted if this future is dropped
+    let mut listeners = JoinSet::new();
+
+    // keyed by object path, as that is all the removal signal provides
+    let mut tasks = loaded
+        .into_iter()
+        .map(|device| {
+            let path = device.path.clone();
+            let task = spawn_watch_device(&mut listeners, device, options.debounce, store, tx);
+            (path, task)
+        })
+        .collect::<HashMap<_, _>>();
+
+    if let Some((stream, online)) = line_power {
+        listeners.spawn(watch_line_power(stream, online, store.clone(), tx.clone()));
+    }
+
+    if options.bluetooth_batteries {
+        match upower::create_bluez_proxy(upower.inner().connection()).await {
+            Ok(bluez) => {
+                listeners.spawn(watch_bluez(bluez, store.clone(), tx.clone()));
+            }
+            Err(err) => warn!("Failed to create BlueZ proxy: {err:?}"),
+        }
+    }
+
+    if let Some((stream, profile)) = power_profile {
+        listeners.spawn(watch_power_profile(
+            stream,
+            profile,
+            store.clone(),
+            tx.clone(),
+        ));
+    }
+
+    let Some((mut added, mut removed)) = hotplug else {
+        while let Some(result) = listeners.join_next().await {
+            result?;
+        }
+
+        return Ok(());
+    };
+
+    loop {
+        select! {
+            Some(signal) = added.next() => {
+                let path = match signal.args() {
+                    Ok(args) => OwnedObjectPath::from(args.device),
+                    Err(err) => {
+                        warn!("Received invalid upower signal arguments: {err}");
+                        continue;
+                    }
+                };
+
+                if tasks.contains_key(&path) {
+                    continue;
+                }
+
+                let device = match upower::create_battery(upower, path.clone()).await {
+                    Ok(Some(device)) => {
+                        load_device(device, options.smoothing, options.estimate_time).await
+                    }
+                    Ok(None) => Ok(None),
+                    Err(err) => Err(err),
+                };
+
+                match device {
+                    Ok(Some(device)) => {
+                        let properties = device.properties.clone();
+
+                        store.insert(properties.clone());
+                        tx.send_update(UpowerEvent::Device(
+                            properties.object_path.clone(),
+                            Arc::new(properties),
+                        ))
+                        .await;
+
+                        let task = spawn_watch_device(
+                            &mut listeners,
+                            device,
+                            options.debounce,
+                            store,
+                            tx,
+                        );
+                        tasks.insert(path, task);
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!("Failed to load upower device {path}: {err:?}"),
+                }
+            }
+            Some(signal) = removed.next() => {
+                let path = match signal.args() {
+                    Ok(args) => OwnedObjectPath::from(args.device),
+                    Err(err) => {
+                        warn!("Received invalid upower signal arguments: {err}");
+                        continue;
+                    }
+                };
+
+                if let Some((key, handle)) = tasks.remove(&path) {
+                    handle.abort();
+                    store.remove(&key);
+                    tx.send_update(UpowerEvent::Remove(key)).await;
+                }
+            }
+            // reap finished and cancelled listeners so that they do not accumulate
+            Some(_) = listeners.join_next() => {}
+            else => break,
+        }
+    }
+
+    // the signal streams only close if the connection is gone,
+    // so stop any listeners that are still running
+    listeners.shutdown().await;
+
+    Ok(())
+}
+
+/// A device with its initial properties loaded,
+/// ready to be watched for changes.
+struct LoadedDevice {
+    path: OwnedObjectPath,
+    properties: UpowerProperties,
+    stream: Boxed<UpowerProperties>,
+    smoother: EstimateSmoother,
+}
+
+/// Subscribes to changes on the device and fetches its initial properties,
+/// applying smoothing to the estimates.
+///
+/// If `estimate` is set, any unavailable estimate is calculated from the energy rate.
+///
+/// Returns `None` if the change stream closes before the properties are read.
+async fn load_device(
+    device: Device,
+    smoothing: f64,
+    estimate: bool,
+) -> Result<Option<LoadedDevice>> {
+    let path = device.path();
+    let mut stream = device.receive_properties().await?;
+
+    if estimate {
+        stream = stream
+            .map(|mut properties| {
+                estimate_time(&mut properties);
+                properties
+            })
+            .boxed();
+    }
+
+    let Some(mut properties) = stream.next().await else {
+        return Ok(None);
+    };
+
+    let mut smoother = EstimateSmoother::new(smoothing);
+    smoother.apply(&mut properties);
+
+    Ok(Some(LoadedDevice {
+        path,
+        properties,
+        stream,
+        smoother,
+    }))
+}
+
+/// Spawns a listener for changes on the loaded device,
+/// returning its key and a handle to cancel the listener.
+fn spawn_watch_device(
+    listeners: &mut JoinSet<()>,
+    device: LoadedDevice,
+    debounce: Duration,
+    store: &Arc<StoreHandle>,
+    tx: &mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) -> (String, AbortHandle) {
+    let key = device.properties.object_path;
+
+    let handle = listeners.spawn(watch_device(
+        key.clone(),
+        device.stream,
+        device.smoother,
+        debounce,
+        store.clone(),
+        tx.clone(),
+    ));
+
+    (key, handle)
+}
+
+/// Listens for property changes on a single device,
+/// sending each updated set of properties to the widgets.
+async fn watch_device(
+    key: String,
+    mut stream: Boxed<UpowerProperties>,
+    mut smoother: EstimateSmoother,
+    debounce: Duration,
+    store: Arc<StoreHandle>,
+    tx: mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    let mut is_closed = false;
+    let mut last = None;
+
+    while !is_closed && let Some(mut properties) = stream.next().await {
+        // coalesce any further changes within the window,
+        // keeping only the latest properties
+        if !debounce.is_zero() {
+            let deadline = Instant::now() + debounce;
+
+            loop {
+                match timeout_at(deadline, stream.next()).await {
+                    Ok(Some(next)) => properties = next,
+                    Ok(None) => {
+                        is_closed = true;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+
+        smoother.apply(&mut properties);
+        store.insert(properties.clone());
+
+        // unrelated properties, such as the voltage, change often
+        // without affecting anything that is displayed
+        let event = UpowerEvent::Device(key.clone(), Arc::new(properties));
+        tx.send_update_if_changed(event, &mut last).await;
+    }
+}
+
+/// Listens for changes to the line power device,
+/// sending an update to the widgets whenever it goes on or offline.
+async fn watch_line_power(
+    mut stream: Boxed<UpowerProperties>,
+    mut online: bool,
+    store: Arc<StoreHandle>,
+    tx: mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    while let Some(properties) = stream.next().await {
+        if properties.online == online {
+            continue;
+        }
+
+        online = properties.online;
+
+        store.set_ac_online(online);
+        tx.send_update(UpowerEvent::AcOnline(online)).await;
+    }
+}
+
+/// Reads the Bluetooth batteries reported by BlueZ,
+/// sending an update to the widgets for each added, changed or removed battery.
+///
+/// The batteries are re-read whenever BlueZ adds or removes an interface,
+/// and every [`BLUEZ_POLL_INTERVAL`] to pick up percentage changes.
+/// Any device already tracked through UPower is skipped.
+///
+/// Returns once the signal streams close or the bus connection is lost,
+/// so that [`watch_devices`] can tell the connection has gone.
+async fn watch_bluez(
+    bluez: ObjectManagerProxy<'static>,
+    store: Arc<StoreHandle>,
+    tx: mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    let (mut added, mut removed) = match (
+        bluez.receive_interfaces_added().await,
+        bluez.receive_interfaces_removed().await,
+    ) {
+        (Ok(added), Ok(removed)) => (added, removed),
+        (Err(err), _) | (_, Err(err)) => {
+            warn!("Failed to subscribe to BlueZ signals: {err}");
+            return;
+        }
+    };
+
+    let mut known = HashMap::<String, UpowerProperties>::new();
+
+    loop {
+        match upower::bluez_batteries(&bluez).await {
+            Ok(mut batteries) => {
+                store.retain_untracked(&mut batteries);
+
+                for key in known.keys().filter(|key| !batteries.contains_key(*key)) {
+                    tx.send_update(UpowerEvent::Remove(key.clone())).await;
+                }
+
+                for (key, properties) in &batteries {
+                    if known.get(key) != Some(properties) {
+                        tx.send_update(UpowerEvent::Device(
+                            key.clone(),
+                            Arc::new(properties.clone()),
+                        ))
+                        .await;
+                    }
+                }
+
+                known = batteries;
+            }
+            Err(err) if is_connection_error(&err) => {
+                debug!("Lost connection while reading BlueZ batteries: {err:?}");
+                return;
+            }
+            // BlueZ not running is not an error, as Bluetooth may be disabled
+            Err(err) => debug!("Failed to read BlueZ batteries: {err:?}"),
+        }
+
+        select! {
+            () = sleep(BLUEZ_POLL_INTERVAL) => {}
+            signal = added.next() => {
+                if signal.is_none() {
+                    return;
+                }
+            }
+            signal = removed.next() => {
+                if signal.is_none() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Checks whether the error was caused by the bus connection failing,
+/// rather than by the remote service.
+fn is_connection_error(err: &Report) -> bool {
+    let err = match err.downcast_ref::<zbus::fdo::Error>() {
+        Some(zbus::fdo::Error::ZBus(err)) => Some(err),
+        Some(_) => None,
+        None => err.downcast_ref::<zbus::Error>(),
+    };
+
+    matches!(err, Some(zbus::Error::InputOutput(_)))
+}
+
+/// Listens for changes to the active power profile,
+/// sending an update to the widgets whenever it changes.
+async fn watch_power_profile(
+    mut stream: PropertyStream<'static, String>,
+    mut profile: String,
+    store: Arc<StoreHandle>,
+    tx: mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    while let Some(change) = stream.next().await {
+        let new_profile = match change.get().await {
+            Ok(new_profile) => new_profile,
+            Err(err) => {
+                warn!("Failed to get active power profile: {err}");
+                continue;
+            }
+        };
+
+        if new_profile == profile {
+            continue;
+        }
+
+        profile = new_profile;
+
+        store.set_power_profile(profile.clone());
+        tx.send_update(UpowerEvent::PowerProfile(profile.clone()))
+            .await;
+    }
+}
+
+/// Changes the active power profile.
+///
+/// The new profile is picked up by [`watch_power_profile`] on success.
+/// On failure, the widgets are told so that the profile buttons can be disabled.
+async fn set_power_profile(
+    power_profiles: ClientRetry<PowerProfilesProxy<'static>>,
+    profile: String,
+    tx: mpsc::Sender<ModuleUpdateEvent<UpowerEvent>>,
+) {
+    debug!("Setting power profile to '{profile}'");
+
+    let Some(power_profiles) = power_profiles.try_get().await else {
+        warn!("Failed to set power profile to '{profile}': power-profiles-daemon is unavailable");
+        tx.send_update(UpowerEvent::PowerProfileFailed).await;
+        return;
+    };
+
+    if let Err(err) = power_profiles.set_active_profile(&profile).await {
+        warn!("Failed to set power profile to '{profile}': {err}");
+        tx.send_update(UpowerEvent::PowerProfileFailed).await;
+    }
+}
+
+/// Creates a level bar to show the battery percentage.
+///
+/// The bar uses the `critical`, `low`, `high` and `full` offsets,
+/// which are applied as CSS classes to its fill block.
+fn create_level_bar(orientation: Orientation) -> LevelBar {
+    let level_bar = LevelBar::builder()
+        .orientation(orientation)
+        .min_value(0.0)
+        .max_value(100.0)
+        .build();
+
+    level_bar.add_class("level");
+
+    // fill upwards on vertical bars
+    level_bar.set_inverted(orientation == Orientation::Vertical);
+
+    for name in ["low", "high", "full"] {
+        level_bar.remove_offset_value(Some(name));
+    }
+
+    level_bar.add_offset_value("critical", 10.0);
+    level_bar.add_offset_value("low", 25.0);
+    level_bar.add_offset_value("high", 99.0);
+    level_bar.add_offset_value("full", 100.0);
+
+    level_bar
+}
+
+/// Gets the CSS `color` of the icon in its current state.
+fn icon_color(icon: &gtk::Image) -> RGBA {
+    icon.style_context().color(icon.state_flags())
+}
+
+/// Sets the state and critical classes on the icon,
+/// removing any classes from the previous state.
+fn set_icon_classes(icon: &gtk::Image, properties: &UpowerProperties, critical_threshold: f64) {
+    for state in ALL_STATES {
+        icon.remove_class(&format!("icon-{}", state_class(state)));
+    }
+
+    icon.add_class(&format!("icon-{}", state_class(properties.state)));
+
+    if properties.percentage <= critical_threshold {
+        icon.add_class("icon-critical");
+    } else {
+        icon.remove_class("icon-critical");
+    }
+}
+
+const ALL_STATES: [BatteryState; 7] = [
+    BatteryState::Unknown,
+    BatteryState::Charging,
+    BatteryState::Discharging,
+    BatteryState::Empty,
+    BatteryState::FullyCharged,
+    BatteryState::PendingCharge,
+    BatteryState::PendingDischarge,
+];
+
+/// Gets the CSS class name for a device,
+/// such as `battery-BAT0`.
+///
+/// This uses the native path, falling back to the model,
+/// with any characters which are not valid in a class name replaced by `-`.
+fn device_class(properties: &UpowerProperties) -> String {
+    let name = if properties.native_path.is_empty() {
+        &properties.model
+    } else {
+        &properties.native_path
+    };
+
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    format!("battery-{name}")
+}
+
+/// Shows a charging battery at or above `full_at` percent as fully charged.
+///
+/// Returns the properties unchanged if the battery is not in one of the `charging_states`
+/// or is below `full_at`.
+fn treat_as_full(
+    properties: &Arc<UpowerProperties>,
+    full_at: f64,
+    tokens: &TokenConfig,
+) -> Arc<UpowerProperties> {
+    let is_charging = tokens.is_charging(properties.state);
+
+    if !is_charging || properties.percentage < full_at {
+        return properties.clone();
+    }
+
+    let mut properties = UpowerProperties::clone(properties);
+    properties.state = BatteryState::FullyCharged;
+    properties.icon_name = String::from("battery-full-charged-symbolic");
+    properties.time_to_full = 0;
+
+    Arc::new(properties)
+}
+
+/// Gets the CSS class name for the widget button in a battery state.
+///
+/// These are coarser than [`state_class`],
+/// grouping both pending states together.
+const fn button_state_class(state: BatteryState) -> Option<&'static str> {
+    match state {
+        BatteryState::Unknown => None,
+        BatteryState::Charging => Some("charging"),
+        BatteryState::Discharging => Some("discharging"),
+        BatteryState::Empty => Some("empty"),
+        BatteryState::FullyCharged => Some("full"),
+        BatteryState::PendingCharge | BatteryState::PendingDischarge => Some("pending"),
+    }
+}
+
+/// Gets the CSS class name for a battery state.
+const fn state_class(state: BatteryState) -> &'static str {
+    match state {
+        BatteryState::Unknown => "unknown",
+        BatteryState::Charging => "charging",
+        BatteryState::Discharging => "discharging",
+        BatteryState::Empty => "empty",
+        BatteryState::FullyCharged => "fully-charged",
+        BatteryState::PendingCharge => "pending-charge",
+        BatteryState::PendingDischarge => "pending-discharge",
+    }
+}
+
+/// Replaces the formatting tokens in `format`
+/// with the values for the provided device.
+pub fn format_properties(
+    format: &str,
+    properties: &UpowerProperties,
+    ac_online: Option<bool>,
+    power_profile: Option<&str>,
+    device_count: usize,
+    tokens: &TokenConfig,
+) -> String {
+    format_tokens(
+        format,
+        properties,
+        ac_online,
+        power_profile,
+        device_count,
+        tokens,
+        false,
+    )
+}
+
+/// Replaces the formatting tokens in a label format,
+/// returning Pango markup ready to be set on the label.
+///
+/// If the format contains markup, it is used verbatim,
+/// and each token value is escaped to avoid device names or labels breaking the markup.
+/// Otherwise, the whole label is escaped.
+fn format_label(
+    format: &str,
+    properties: &UpowerProperties,
+    ac_online: Option<bool>,
+    power_profile: Option<&str>,
+    device_count: usize,
+    tokens: &TokenConfig,
+) -> String {
+    let is_markup = format.contains('<');
+    let label = format_tokens(
+        format,
+        properties,
+        ac_online,
+        power_profile,
+        device_count,
+        tokens,
+        is_markup,
+    );
+
+    if is_markup {
+        label
+    } else {
+        glib::markup_escape_text(&label).to_string()
+    }
+}
+
+/// Replaces the formatting tokens in `format`,
+/// escaping each value for Pango markup if `escape` is set.
+fn format_tokens(
+    format: &str,
+    properties: &UpowerProperties,
+    ac_online: Option<bool>,
+    power_profile: Option<&str>,
+    device_count: usize,
+    tokens: &TokenConfig,
+    escape: bool,
+) -> String {
+    let time_format = &tokens.time;
+
+    let state = properties.state;
+
+    let seconds = if tokens.is_plugged_in(state) {
+        properties.time_to_full
+    } else {
+        properties.time_to_empty
+    };
+
+    let is_active = matches!(state, BatteryState::Charging | BatteryState::Discharging);
+
+    let time_remaining = if seconds == 0 && is_active {
+        time_format.calculating_text().to_string()
+    } else if seconds != 0 && !time_format.is_within_limits(seconds) {
+        String::new()
+    } else {
+        seconds_to_string(seconds, time_format).unwrap_or_default()
+    };
+
+    // unlike `{time_remaining}`, these are always empty when unavailable
+    let explicit_time = |seconds| {
+        if seconds == 0 {
+            String::new()
+        } else {
+            seconds_to_string(seconds, time_format).unwrap_or_default()
+        }
+    };
+
+    let lookup = |token: &str| match token {
+        "percentage" => Some(tokens.percentage(properties.percentage)),
+        "model" => Some(properties.model.clone()),
+        "time_remaining" => Some(time_remaining.clone()),
+        "seconds_remaining" => Some(seconds.to_string()),
+        "time_to_full" => Some(explicit_time(properties.time_to_full)),
+        "time_to_empty" => Some(explicit_time(properties.time_to_empty)),
+        "state" => Some(tokens.state_labels.get(state).to_string()),
+        "icon_text" => Some(tokens.font_icons.glyph(properties).to_string()),
+        "ac_online" => Some(tokens.ac_online(ac_online).to_string()),
+        "is_present" => Some(tokens.is_present(properties.is_present).to_string()),
+        "power_profile" => Some(power_profile.unwrap_or_default().to_string()),
+        "device_count" => Some(device_count.to_string()),
+        "charge_limit" => Some(
+            properties
+                .charge_limit()
+                .map(|limit| limit.to_string())
+                .unwrap_or_default(),
+        ),
+        _ => None,
+    };
+
+    if escape {
+        replace_tokens_escaped(format, lookup)
+    } else {
+        replace_tokens(format, lookup)
+    }
+}
+
+/// Publishes the device's values as `battery.<token>` ironvars,
+/// using the same values as the matching format tokens.
+///
+/// Each widget publishes the values for its primary device,
+/// and unchanged values are not broadcast again.
+#[cfg(feature = "ipc")]
+fn publish_variables(properties: &UpowerProperties, state: &UpowerState, tokens: &TokenConfig) {
+    let variable_manager = Ironbar::variable_manager();
+
+    for token in ["percentage", "state", "time_remaining"] {
+        let value = format_properties(
+            &format!("{{{token}}}"),
+            properties,
+            state.ac_online,
+            state.power_profile.as_deref(),
+            state.devices.len(),
+            tokens,
+        );
+        variable_manager.publish(&format!("battery.{token}"), value);
+    }
+}
+
+/// Gets the device to show on the bar widget.
+///
+/// If `prefer_active` is set, this is the first device which is charging or discharging,
+/// falling back to the [primary device](primary_device).
+fn bar_device<'a>(
+    properties_map: &'a PropertiesMap,
+    device_order: &[String],
+    prefer_active: bool,
+) -> Option<&'a Arc<UpowerProperties>> {
+    let active = prefer_active
+        .then(|| {
+            sorted_devices(properties_map, device_order)
+                .into_iter()
+                .find(|properties| {
+                    !properties.is_bluez
+                        && matches!(
+                            properties.state,
+                            BatteryState::Charging | BatteryState::Discharging
+                        )
+                })
+        })
+        .flatten();
+
+    active.or_else(|| primary_device(properties_map, device_order))
+}
+
+/// Gets the device to show on the bar widget by default.
+///
+/// When tracking multiple devices, this is the first in `device_order`.
+pub fn primary_device<'a, P: Borrow<UpowerProperties>>(
+    properties_map: &'a HashMap<String, P>,
+    device_order: &[String],
+) -> Option<&'a P> {
+    properties_map
+        .values()
+        .filter(|properties| !(*properties).borrow().is_bluez)
+        .min_by_key(|properties| device_sort_key((*properties).borrow(), device_order))
+}
+
+/// Gets the devices sorted by `device_order`.
+fn sorted_devices<'a, P: Borrow<UpowerProperties>>(
+    properties_map: &'a HashMap<String, P>,
+    device_order: &[String],
+) -> Vec<&'a P> {
+    let mut devices = properties_map.values().collect::<Vec<_>>();
+    devices.sort_by_key(|properties| device_sort_key((*properties).borrow(), device_order));
+    devices
+}
+
+/// Gets the key to sort devices by.
+///
+/// Devices listed in `device_order` come first, in the listed order,
+/// followed by all other devices sorted by native path.
+fn device_sort_key<'a>(
+    properties: &'a UpowerProperties,
+    device_order: &[String],
+) -> (usize, &'a str) {
+    let position = device_order
+        .iter()
+        .position(|entry| *entry == properties.native_path || *entry == properties.model)
+        .unwrap_or(device_order.len());
+
+    (position, &properties.native_path)
+}
+
+/// Gets the text shown for a device in the popup.
+///
+/// When `show_names` is set, this is prefixed with the device percentage,
+/// since the device name is shown in a header above it.
+fn popup_text(properties: &UpowerProperties, tokens: &TokenConfig, show_names: bool) -> String {
+    let details = popup_details(properties, tokens);
+
+    if !show_names {
+        return details;
+    }
+
+    let mut text = format!("{}%", properties.percentage.round());
+
+    if !details.is_empty() {
+        text.push_str(", ");
+        text.push_str(&details);
+    }
+
+    text
+}
+
+/// Sets whether the layer shell window containing the popup content receives keyboard input.
+fn set_popup_keyboard_interactivity(container: &gtk::Box, interactivity: bool) {
+    if let Some(window) = container
+        .toplevel()
+        .and_then(|window| window.downcast::<gtk::Window>().ok())
+    {
+        window.set_keyboard_interactivity(interactivity);
+    }
+}
+
+/// Gets the description of a device announced by screen readers,
+/// such as `BAT0: 81%, Charging, Full in 40m`.
+fn accessible_text(properties: &UpowerProperties, tokens: &TokenConfig) -> String {
+    let mut text = format!(
+        "{}: {}%, {}",
+        properties.title(),
+        properties.percentage.round(),
+        battery_state_to_string(properties.state)
+    );
+
+    let details = popup_details(properties, tokens);
+    if !details.is_empty() {
+        text.push_str(", ");
+        text.push_str(&details);
+    }
+
+    text
+}
+
+/// Gets the time estimate text shown for a device in the popup.
+///
+/// The time until full is shown for any of the configured charging states.
+fn popup_details(properties: &UpowerProperties, tokens: &TokenConfig) -> String {
+    let time_format = &tokens.time;
+
+    match properties.state {
+        state if tokens.is_charging(state) => {
+            let ttf = properties.time_to_full;
+            if ttf > 0 {
+                format!(
+                    "Full in {}",
+                    seconds_to_string(ttf, time_format).unwrap_or_default()
+                )
+            } else if properties.state == BatteryState::Charging {
+                time_format.calculating_text().to_string()
+            } else {
+                String::new()
+            }
+        }
+        BatteryState::Discharging | BatteryState::PendingDischarge => {
+            let tte = properties.time_to_empty;
+            if tte > 0 {
+                format!(
+                    "Empty in {}",
+                    seconds_to_string(tte, time_format).unwrap_or_default()
+                )
+            } else if properties.state == BatteryState::Discharging {
+                time_format.calculating_text().to_string()
+            } else {
+                String::new()
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_label_tokens() {
+        let mut properties = UpowerProperties::from_map(&HashMap::new());
+        properties.model = String::from("Tom & Jerry");
+
+        let format = |format: &str| {
+            format_label(format, &properties, None, None, 1, &TokenConfig::default())
+        };
+
+        assert_eq!(format("<b>{model}</b>"), "<b>Tom &amp; Jerry</b>");
+        assert_eq!(format("{model} & more"), "Tom &amp; Jerry &amp; more");
+    }
+}
@@ -0,0 +1,114 @@
+use super::state_class;
+use crate::clients::upower::{BatteryState, UpowerProperties};
+use crate::gtk_helpers::IronbarGtkExt;
+use gtk::DrawingArea;
+use gtk::cairo::Context;
+use gtk::glib::Propagation;
+use gtk::prelude::*;
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+use tracing::error;
+
+/// Opacity of the unfilled portion of the ring,
+/// relative to the foreground colour.
+const TRACK_ALPHA: f64 = 0.25;
+
+/// A circular battery indicator,
+/// drawn as an arc whose length matches the charge percentage.
+///
+/// The ring uses the CSS `color` of the widget,
+/// and has a class matching the current battery state so it can be styled per state.
+#[derive(Debug, Clone)]
+pub struct Ring {
+    area: DrawingArea,
+    percentage: Rc<Cell<f64>>,
+    state: Rc<Cell<BatteryState>>,
+}
+
+impl Ring {
+    pub fn new(size: i32, thickness: f64) -> Self {
+        let area = DrawingArea::new();
+        area.add_class("ring");
+        area.set_size_request(size, size);
+
+        let percentage = Rc::new(Cell::new(0.0));
+
+        {
+            let percentage = percentage.clone();
+            area.connect_draw(move |area, cr| {
+                if let Err(err) = draw(area, cr, percentage.get(), thickness) {
+                    error!("{err}");
+                }
+
+                Propagation::Proceed
+            });
+        }
+
+        let state = Rc::new(Cell::new(BatteryState::Unknown));
+        area.add_class(state_class(BatteryState::Unknown));
+
+        Self {
+            area,
+            percentage,
+            state,
+        }
+    }
+
+    pub fn widget(&self) -> &DrawingArea {
+        &self.area
+    }
+
+    /// Updates the ring to match the device,
+    /// and queues a redraw.
+    pub fn update(&self, properties: &UpowerProperties) {
+        self.percentage.set(properties.percentage);
+
+        let old_state = self.state.replace(properties.state);
+        if old_state != properties.state {
+            self.area.remove_class(state_class(old_state));
+            self.area.add_class(state_class(properties.state));
+        }
+
+        self.area.queue_draw();
+    }
+}
+
+fn draw(
+    area: &DrawingArea,
+    cr: &Context,
+    percentage: f64,
+    thickness: f64,
+) -> Result<(), gtk::cairo::Error> {
+    let width = f64::from(area.allocated_width());
+    let height = f64::from(area.allocated_height());
+
+    let radius = (width.min(height) - thickness) / 2.0;
+    if radius <= 0.0 {
+        return Ok(());
+    }
+
+    let (x, y) = (width / 2.0, height / 2.0);
+    let color = area.style_context().color(area.state_flags());
+
+    cr.set_line_width(thickness);
+
+    cr.set_source_rgba(
+        color.red(),
+        color.green(),
+        color.blue(),
+        color.alpha() * TRACK_ALPHA,
+    );
+    cr.arc(x, y, radius, 0.0, 2.0 * PI);
+    cr.stroke()?;
+
+    // start from the top and fill clockwise
+    let start = -PI / 2.0;
+    let end = start + 2.0 * PI * (percentage / 100.0).clamp(0.0, 1.0);
+
+    cr.set_source_rgba(color.red(), color.green(), color.blue(), color.alpha());
+    cr.arc(x, y, radius, start, end);
+    cr.stroke()?;
+
+    Ok(())
+}
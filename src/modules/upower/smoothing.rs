@@ -0,0 +1,112 @@
+use crate::clients::upower::UpowerProperties;
+use tracing::warn;
+
+/// The highest usable smoothing factor.
+/// At `1` new samples are ignored entirely, so the estimate would never change.
+const MAX_FACTOR: f64 = 0.99;
+
+/// Exponentially smooths the time estimates of a single device.
+///
+/// Each new estimate is blended with the previous smoothed value,
+/// weighting the previous value by the smoothing `factor`.
+/// A factor of `0` disables smoothing.
+#[derive(Debug, Clone, Copy)]
+pub struct EstimateSmoother {
+    factor: f64,
+    time_to_empty: Option<f64>,
+    time_to_full: Option<f64>,
+}
+
+impl EstimateSmoother {
+    pub fn new(factor: f64) -> Self {
+        Self {
+            factor: clamp_factor(factor),
+            time_to_empty: None,
+            time_to_full: None,
+        }
+    }
+
+    /// Replaces the estimates of `properties` with their smoothed values.
+    pub fn apply(&mut self, properties: &mut UpowerProperties) {
+        if self.factor == 0.0 {
+            return;
+        }
+
+        properties.time_to_empty = smooth(
+            &mut self.time_to_empty,
+            properties.time_to_empty,
+            self.factor,
+        );
+        properties.time_to_full =
+            smooth(&mut self.time_to_full, properties.time_to_full, self.factor);
+    }
+}
+
+/// Clamps the factor into `[0, MAX_FACTOR]`,
+/// warning if it was out of range.
+fn clamp_factor(factor: f64) -> f64 {
+    if factor.is_nan() {
+        warn!("Invalid upower smoothing factor {factor}, disabling smoothing");
+        return 0.0;
+    }
+
+    let clamped = factor.clamp(0.0, MAX_FACTOR);
+    if clamped != factor {
+        warn!("Upower smoothing factor {factor} is out of range, using {clamped}");
+    }
+
+    clamped
+}
+
+/// Blends the sample into the previous value.
+///
+/// UPower reports `0` when an estimate is unavailable,
+/// such as after switching between charging and discharging,
+/// so this resets the smoothed value rather than blending towards zero.
+fn smooth(previous: &mut Option<f64>, sample: i64, factor: f64) -> i64 {
+    if sample == 0 {
+        *previous = None;
+        return 0;
+    }
+
+    let sample = sample as f64;
+    let value = previous.map_or(sample, |previous| {
+        previous.mul_add(factor, sample * (1.0 - factor))
+    });
+
+    *previous = Some(value);
+    value.round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_samples() {
+        let mut previous = None;
+        assert_eq!(smooth(&mut previous, 1000, 0.5), 1000);
+        assert_eq!(smooth(&mut previous, 2000, 0.5), 1500);
+    }
+
+    #[test]
+    fn clamps_factor() {
+        assert_eq!(clamp_factor(-1.0), 0.0);
+        assert_eq!(clamp_factor(f64::NAN), 0.0);
+        assert_eq!(clamp_factor(0.5), 0.5);
+        assert_eq!(clamp_factor(1.0), MAX_FACTOR);
+
+        // the estimate should still move towards new samples at the highest factor
+        let mut previous = None;
+        smooth(&mut previous, 1000, clamp_factor(1.0));
+        assert!(smooth(&mut previous, 2000, clamp_factor(1.0)) > 1000);
+    }
+
+    #[test]
+    fn resets_on_zero() {
+        let mut previous = None;
+        smooth(&mut previous, 1000, 0.5);
+        assert_eq!(smooth(&mut previous, 0, 0.5), 0);
+        assert_eq!(smooth(&mut previous, 3000, 0.5), 3000);
+    }
+}
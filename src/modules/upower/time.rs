@@ -0,0 +1,269 @@
+use super::format::replace_tokens;
+use color_eyre::Result;
+use serde::Deserialize;
+use std::fmt::Write;
+
+const DAY: i64 = 24 * 60 * 60;
+const HOUR: i64 = 60 * 60;
+const MINUTE: i64 = 60;
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TimeFormat {
+    /// The template used to format time estimates, such as `{time_remaining}`.
+    ///
+    /// The `{d}`, `{h}`, `{m}` and `{s}` tokens are replaced
+    /// with the days, hours, minutes and seconds respectively.
+    /// Tokens accept a width and fill, for example `{m:02}`.
+    ///
+    /// Larger units which are not in the template are folded into the next largest unit,
+    /// so `{h}:{m:02}` shows 26 hours as `26:00`.
+    ///
+    /// If not set, the `1d 2h 30m` layout is used, omitting any units which are zero.
+    ///
+    /// **Default**: `null`
+    time_format: Option<String>,
+
+    /// The text to show in place of a time estimate of zero.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    zero_time_text: String,
+
+    /// The text to show in place of a time estimate of zero
+    /// while the battery is actively charging or discharging,
+    /// as UPower reports zero while it recalculates the estimate.
+    ///
+    /// **Default**: `Calculating…`
+    calculating_text: Option<String>,
+
+    /// Whether to show the seconds for estimates under a minute,
+    /// rather than `<1m`. Only applies to the default layout.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    show_seconds: bool,
+
+    /// The text placed between each unit in the default layout.
+    ///
+    /// **Default**: `" "`
+    time_separator: Option<String>,
+
+    /// The labels placed after each unit in the default layout.
+    /// See [time units](#time-units).
+    ///
+    /// **Default**: `{}`
+    #[serde(default)]
+    time_units: TimeUnits,
+
+    /// The shortest estimate to show in `{time_remaining}`, in minutes.
+    /// Shorter estimates are left empty,
+    /// which avoids the estimate flickering as the battery nears empty or full.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    min_time_minutes: u64,
+
+    /// The longest estimate to show in `{time_remaining}`, in minutes.
+    /// Longer estimates are left empty,
+    /// so the estimate only appears once it is low enough to be of interest.
+    ///
+    /// **Default**: `null`
+    max_time_minutes: Option<u64>,
+}
+
+/// The labels for each unit in the default time layout.
+#[derive(Debug, Default, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TimeUnits {
+    /// **Default**: `d`
+    days: Option<String>,
+    /// **Default**: `h`
+    hours: Option<String>,
+    /// **Default**: `m`
+    minutes: Option<String>,
+    /// **Default**: `s`
+    seconds: Option<String>,
+}
+
+impl TimeUnits {
+    fn days(&self) -> &str {
+        self.days.as_deref().unwrap_or("d")
+    }
+
+    fn hours(&self) -> &str {
+        self.hours.as_deref().unwrap_or("h")
+    }
+
+    fn minutes(&self) -> &str {
+        self.minutes.as_deref().unwrap_or("m")
+    }
+
+    fn seconds(&self) -> &str {
+        self.seconds.as_deref().unwrap_or("s")
+    }
+}
+
+impl TimeFormat {
+    pub fn calculating_text(&self) -> &str {
+        self.calculating_text.as_deref().unwrap_or("Calculating…")
+    }
+
+    /// Checks whether an estimate is within `min_time_minutes` and `max_time_minutes`.
+    pub fn is_within_limits(&self, seconds: i64) -> bool {
+        let minutes = u64::try_from(seconds / MINUTE).unwrap_or_default();
+
+        minutes >= self.min_time_minutes && self.max_time_minutes.is_none_or(|max| minutes <= max)
+    }
+}
+
+pub fn seconds_to_string(seconds: i64, format: &TimeFormat) -> Result<String> {
+    if seconds == 0 {
+        return Ok(format.zero_time_text.clone());
+    }
+
+    match &format.time_format {
+        Some(template) => Ok(format_template(seconds, template)),
+        None => format_default(seconds, format),
+    }
+}
+
+fn format_default(seconds: i64, format: &TimeFormat) -> Result<String> {
+    let units = &format.time_units;
+
+    // otherwise nothing would be shown for the final minute
+    if seconds < MINUTE {
+        return Ok(if format.show_seconds {
+            format!("{seconds}{}", units.seconds())
+        } else {
+            format!("<1{}", units.minutes())
+        });
+    }
+
+    let separator = format.time_separator.as_deref().unwrap_or(" ");
+
+    let parts = [
+        (seconds / DAY, units.days()),
+        ((seconds % DAY) / HOUR, units.hours()),
+        ((seconds % HOUR) / MINUTE, units.minutes()),
+    ];
+
+    let mut time_string = String::new();
+    for (value, unit) in parts.into_iter().filter(|(value, _)| *value > 0) {
+        if !time_string.is_empty() {
+            time_string.push_str(separator);
+        }
+
+        write!(time_string, "{value}{unit}")?;
+    }
+
+    Ok(time_string)
+}
+
+fn format_template(seconds: i64, template: &str) -> String {
+    let uses_unit = |unit: char| {
+        template.contains(&format!("{{{unit}}}")) || template.contains(&format!("{{{unit}:"))
+    };
+
+    let mut remaining = seconds;
+    let mut take_unit = |unit: char, size: i64| {
+        if uses_unit(unit) {
+            let value = remaining / size;
+            remaining %= size;
+            value
+        } else {
+            0
+        }
+    };
+
+    let days = take_unit('d', DAY);
+    let hours = take_unit('h', HOUR);
+    let minutes = take_unit('m', MINUTE);
+    let seconds = take_unit('s', 1);
+
+    replace_tokens(template, |token| match token {
+        "d" => Some(days.to_string()),
+        "h" => Some(hours.to_string()),
+        "m" => Some(minutes.to_string()),
+        "s" => Some(seconds.to_string()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(time_format: &str) -> TimeFormat {
+        TimeFormat {
+            time_format: Some(time_format.to_string()),
+            ..TimeFormat::default()
+        }
+    }
+
+    #[test]
+    fn default_layout() {
+        let format = TimeFormat::default();
+        let time = seconds_to_string(DAY + 2 * HOUR + 30 * MINUTE, &format).unwrap();
+        assert_eq!(time, "1d 2h 30m");
+    }
+
+    #[test]
+    fn default_layout_units() {
+        let format = TimeFormat {
+            time_separator: Some(String::from(", ")),
+            time_units: TimeUnits {
+                hours: Some(String::from(" hr")),
+                minutes: Some(String::from(" min")),
+                ..TimeUnits::default()
+            },
+            ..TimeFormat::default()
+        };
+
+        let time = seconds_to_string(2 * HOUR + 30 * MINUTE, &format).unwrap();
+        assert_eq!(time, "2 hr, 30 min");
+        assert_eq!(seconds_to_string(45, &format).unwrap(), "<1 min");
+    }
+
+    #[test]
+    fn template_folds_days() {
+        let format = template("{h}:{m:02}");
+        let time = seconds_to_string(26 * HOUR + 5 * MINUTE, &format).unwrap();
+        assert_eq!(time, "26:05");
+    }
+
+    #[test]
+    fn default_layout_under_minute() {
+        let format = TimeFormat::default();
+        assert_eq!(seconds_to_string(45, &format).unwrap(), "<1m");
+
+        let format = TimeFormat {
+            show_seconds: true,
+            ..TimeFormat::default()
+        };
+        assert_eq!(seconds_to_string(45, &format).unwrap(), "45s");
+    }
+
+    #[test]
+    fn time_limits() {
+        let format = TimeFormat {
+            min_time_minutes: 2,
+            max_time_minutes: Some(60),
+            ..TimeFormat::default()
+        };
+
+        assert!(!format.is_within_limits(90));
+        assert!(format.is_within_limits(2 * MINUTE));
+        assert!(format.is_within_limits(HOUR));
+        assert!(!format.is_within_limits(HOUR + MINUTE));
+    }
+
+    #[test]
+    fn zero_text() {
+        let format = TimeFormat {
+            zero_time_text: String::from("—"),
+            ..TimeFormat::default()
+        };
+        assert_eq!(seconds_to_string(0, &format).unwrap(), "—");
+    }
+}
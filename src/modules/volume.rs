@@ -257,6 +257,8 @@ impl Module<Button> for VolumeModule {
             },
         );
 
+        self.layout.set_alignment(&button);
+
         let popup = self
             .into_popup(context, info)
             .into_popup_parts(vec![&button]);
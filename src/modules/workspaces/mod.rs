@@ -233,7 +233,7 @@ impl Module<gtk::Box> for WorkspacesModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
-        let container = gtk::Box::new(self.layout.orientation(info), 0);
+        let container = gtk::Box::new(self.layout.orientation(info), self.layout.spacing(0));
 
         let mut button_map = ButtonMap::new();
 
@@ -408,6 +408,8 @@ impl Module<gtk::Box> for WorkspacesModule {
                 });
         }
 
+        self.layout.set_alignment(&container);
+
         Ok(ModuleParts {
             widget: container,
             popup: None,